@@ -1,5 +1,11 @@
 mod quadforest;
 
+pub use quadforest::AdjacencyCSR;
+pub use quadforest::BulkLoader;
+pub use quadforest::CompactionResult;
+pub use quadforest::ForestOfIdentifierQuads;
+pub use quadforest::MatchPage;
+
 // When the `wee_alloc` feature is enabled, use `wee_alloc` as the global
 // allocator.
 #[cfg(feature = "wee_alloc")]
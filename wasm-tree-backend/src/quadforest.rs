@@ -4,15 +4,75 @@ use identifier_forest::IndexingForest4;
 use identifier_forest::TermRole;
 use wasm_bindgen::prelude::*;
 
+use std::cell::Cell;
+use std::cmp::Ordering;
+use std::cmp::Reverse;
+use std::collections::BTreeMap;
+use std::collections::BTreeSet;
+use std::collections::BinaryHeap;
+use std::collections::HashMap;
+use std::collections::HashSet;
+use std::collections::VecDeque;
+
 
 // We write one impl block per function to make it easier to debug (if we have
 // a compile error, the compiler says there is an error in the whole impl block,
 // splitting in different impl blocks helps identifying the bugged function)
 
+/// Identifier of the default graph unless another one is chosen with
+/// `withDefaultGraph`: the one wasm-tree-frontend gives to it
+const DEFAULT_GRAPH: u32 = 0;
+
+/// Default maximum number of quads of the universe explored by
+/// `complementWithin`, see `setMaxComplementUniverse`
+const DEFAULT_MAX_COMPLEMENT_UNIVERSE: usize = 1 << 20;
+
 /// wasm_bindgen annoted adapter of IndexingForest4 intended for wasm-tree-frontend
 #[wasm_bindgen(js_name="ForestOfIdentifierQuads")]
 pub struct ForestOfIdentifierQuads {
-    trees: IndexingForest4
+    trees: IndexingForest4,
+    /// Maximum number of trees, including the base tree, that can be alive
+    max_living_trees: usize,
+    /// If true, queries never build an optional tree
+    indexes_locked: bool,
+    /// Logical clock incremented on every query
+    clock: Cell<u64>,
+    /// For each optional tree, the clock value of the last query it answered
+    last_uses: Vec<Cell<u64>>,
+    /// Number of queries answered by the base tree followed by the number of
+    /// queries answered by each optional tree
+    query_counts: Vec<Cell<u32>>,
+    /// If a transaction is running, what is needed to revert it
+    transaction: Option<Transaction>,
+    /// If the journal is enabled, the `[op, s, p, o, g]` records of the
+    /// modifications, see `exportJournal`
+    journal: Option<Vec<u32>>,
+    /// Size of the dataset when `markSnapshot` was last called
+    snapshot_size: usize,
+    /// If the dataset is frozen, its quads sorted in SPOG order. While it is
+    /// frozen, queries are answered with this list instead of the trees
+    frozen: Option<Vec<[u32; 4]>>,
+    /// If built, a hash set with every quad, used to answer `has`
+    membership_index: Option<HashSet<[u32; 4]>>,
+    /// Identifier of the default graph, for the operations that handle it
+    /// differently from the other graphs
+    default_graph_id: u32,
+    /// If enabled, the sequence number of every quad, see `quadsSince`
+    insertion_sequence: Option<InsertionSequence>,
+    /// If set, an identifier that the inserted quads can not contain, see
+    /// `setStrictSentinel`
+    sentinel: Option<u32>,
+    /// Number of quads that have not been inserted because they contain the
+    /// sentinel
+    number_of_rejected_quads: usize,
+    /// Maximum number of quads of the universe explored by `complementWithin`
+    max_complement_universe: usize,
+    /// If computed since the last modification, the number of distinct terms
+    /// at each position, see `estimateMatchCount`
+    term_cardinalities: Cell<Option<[usize; 4]>>,
+    /// If enabled, the number of quads that use each term at each position,
+    /// see `enableStatistics`
+    statistics: Option<TermStatistics>
 }
 
 #[wasm_bindgen(js_class="ForestOfIdentifierQuads")]
@@ -22,7 +82,80 @@ impl ForestOfIdentifierQuads {
     /// received for them.
     #[wasm_bindgen(constructor)]
     pub fn new() -> Self {
-        Self { trees: IndexingForest4::new() }
+        let trees = IndexingForest4::new();
+        let last_uses = trees.optional_trees.iter().map(|_| Cell::new(0)).collect();
+        let query_counts = (0..=trees.optional_trees.len()).map(|_| Cell::new(0)).collect();
+
+        Self {
+            trees,
+            max_living_trees: usize::MAX,
+            indexes_locked: false,
+            clock: Cell::new(0),
+            last_uses,
+            query_counts,
+            transaction: None,
+            journal: None,
+            snapshot_size: 0,
+            frozen: None,
+            membership_index: None,
+            default_graph_id: DEFAULT_GRAPH,
+            insertion_sequence: None,
+            sentinel: None,
+            number_of_rejected_quads: 0,
+            max_complement_universe: DEFAULT_MAX_COMPLEMENT_UNIVERSE,
+            term_cardinalities: Cell::new(None),
+            statistics: None
+        }
+    }
+}
+
+#[wasm_bindgen(js_class="ForestOfIdentifierQuads")]
+impl ForestOfIdentifierQuads {
+    /// Builds an empty Quad Forest in which the default graph has the
+    /// identifier `default_graph_id` instead of 0.
+    ///
+    /// The datasets built from this one, like the result of `match` or of the
+    /// set operations, use the same identifier.
+    #[wasm_bindgen(js_name = withDefaultGraph)]
+    pub fn with_default_graph(default_graph_id: u32) -> Self {
+        let mut forest = Self::new();
+        forest.default_graph_id = default_graph_id;
+        forest
+    }
+
+    /// Returns the identifier of the default graph
+    #[wasm_bindgen(js_name = defaultGraphId)]
+    pub fn default_graph_id(&self) -> u32 {
+        self.default_graph_id
+    }
+}
+
+impl ForestOfIdentifierQuads {
+    /// Builds an empty dataset with the same default graph identifier
+    fn new_like(&self) -> Self {
+        Self::with_default_graph(self.default_graph_id)
+    }
+}
+
+#[wasm_bindgen(js_class="ForestOfIdentifierQuads")]
+impl ForestOfIdentifierQuads {
+    /// Reserves an identifier, for example one used as a wildcard by the
+    /// caller: the quads that contain it in any position are not inserted
+    /// anymore by `add` and the other insertion functions, and are counted by
+    /// `numberOfRejectedQuads` instead. Passing no identifier allows every
+    /// quad again, which is the default.
+    ///
+    /// The quads that are already in the dataset are not removed.
+    #[wasm_bindgen(js_name = setStrictSentinel)]
+    pub fn set_strict_sentinel(&mut self, sentinel: Option<u32>) {
+        self.sentinel = sentinel;
+    }
+
+    /// Returns the number of quads that have not been inserted because they
+    /// contain the identifier reserved by `setStrictSentinel`
+    #[wasm_bindgen(js_name = numberOfRejectedQuads)]
+    pub fn number_of_rejected_quads(&self) -> usize {
+        self.number_of_rejected_quads
     }
 }
 
@@ -38,7 +171,17 @@ impl ForestOfIdentifierQuads {
 impl ForestOfIdentifierQuads {
     /// Adds the given quad
     pub fn add(&mut self, s: u32, p: u32, o: u32, g: u32) {
-        self.trees.insert([s, p, o, g]);
+        self.insert_quad([s, p, o, g]);
+    }
+}
+
+#[wasm_bindgen(js_class="ForestOfIdentifierQuads")]
+impl ForestOfIdentifierQuads {
+    /// Adds the given quad and returns the number of quads of the dataset
+    #[wasm_bindgen(js_name = addReturningSize)]
+    pub fn add_returning_size(&mut self, s: u32, p: u32, o: u32, g: u32) -> usize {
+        self.insert_quad([s, p, o, g]);
+        self.size()
     }
 }
 
@@ -46,7 +189,93 @@ impl ForestOfIdentifierQuads {
 impl ForestOfIdentifierQuads {
     /// Removes the given quad
     pub fn remove(&mut self, s: u32, p: u32, o: u32, g: u32) {
-        self.trees.delete([s, p, o, g]);
+        self.delete_quad([s, p, o, g]);
+    }
+}
+
+impl ForestOfIdentifierQuads {
+    /// Inserts the quad in every tree and returns true if it was not already
+    /// in the dataset. A quad that contains the sentinel is not inserted.
+    ///
+    /// Every modification of the dataset goes through this function,
+    /// `delete_quad` or `record_change`.
+    fn insert_quad(&mut self, quad: [u32; 4]) -> bool {
+        if let Some(sentinel) = self.sentinel {
+            if quad.contains(&sentinel) {
+                self.number_of_rejected_quads += 1;
+                return false;
+            }
+        }
+
+        let size_before = self.size();
+        self.trees.insert(quad);
+
+        let inserted = self.size() != size_before;
+        if inserted {
+            self.record_change(quad, false);
+        }
+        inserted
+    }
+
+    /// Deletes the quad from every tree and returns true if it was in the
+    /// dataset
+    fn delete_quad(&mut self, quad: [u32; 4]) -> bool {
+        let size_before = self.size();
+        self.trees.delete(quad);
+
+        let deleted = self.size() != size_before;
+        if deleted {
+            self.record_change(quad, true);
+        }
+        deleted
+    }
+
+    /// Records in the running transaction and in the journal, if any, that
+    /// the membership of the quad has changed. Unfreezes the dataset and
+    /// updates the membership index.
+    fn record_change(&mut self, quad: [u32; 4], was_present: bool) {
+        self.update_derived_state(quad, was_present);
+
+        if let Some(transaction) = self.transaction.as_mut() {
+            transaction.changes.push((quad, was_present));
+        }
+
+        if let Some(journal) = self.journal.as_mut() {
+            journal.push(if was_present { JOURNAL_REMOVE } else { JOURNAL_ADD });
+            journal.extend_from_slice(&quad);
+        }
+
+        if let Some(insertion_sequence) = self.insertion_sequence.as_mut() {
+            if was_present {
+                insertion_sequence.remove(&quad);
+            } else {
+                insertion_sequence.insert(quad);
+            }
+        }
+    }
+
+    /// Updates what is computed from the quads after the membership of the
+    /// quad has changed: unfreezes the dataset and updates the membership
+    /// index and the statistics
+    fn update_derived_state(&mut self, quad: [u32; 4], was_present: bool) {
+        self.frozen = None;
+        self.term_cardinalities.set(None);
+
+        if let Some(membership_index) = self.membership_index.as_mut() {
+            if was_present {
+                membership_index.remove(&quad);
+            } else {
+                membership_index.insert(quad);
+            }
+        }
+
+        if let Some(statistics) = self.statistics.as_mut() {
+            if was_present {
+                statistics.remove(&quad);
+            } else {
+                statistics.insert(&quad);
+            }
+        }
     }
 }
 
@@ -54,10 +283,65 @@ impl ForestOfIdentifierQuads {
 impl ForestOfIdentifierQuads {
     /// Returns true if the tree has the specified quad
     pub fn has(&self, s: u32, p: u32, o: u32, g: u32) -> bool {
+        if let Some(membership_index) = self.membership_index.as_ref() {
+            return membership_index.contains(&[s, p, o, g]);
+        }
+
         self.trees.base_tree.0.contains(&self.trees.base_tree.1, &[s, p, o, g])
     }
 }
 
+#[wasm_bindgen(js_class="ForestOfIdentifierQuads")]
+impl ForestOfIdentifierQuads {
+    /// Adds the quad described by the 4 identifiers of the slice
+    #[wasm_bindgen(js_name = addQuad)]
+    pub fn add_quad(&mut self, quad: &[u32]) {
+        assert!(quad.len() == 4);
+        self.add(quad[0], quad[1], quad[2], quad[3]);
+    }
+
+    /// Returns true if the dataset has the quad described by the 4
+    /// identifiers of the slice
+    #[wasm_bindgen(js_name = hasQuad)]
+    pub fn has_quad(&self, quad: &[u32]) -> bool {
+        assert!(quad.len() == 4);
+        self.has(quad[0], quad[1], quad[2], quad[3])
+    }
+
+    /// Removes the quad described by the 4 identifiers of the slice
+    #[wasm_bindgen(js_name = removeQuad)]
+    pub fn remove_quad(&mut self, quad: &[u32]) {
+        assert!(quad.len() == 4);
+        self.remove(quad[0], quad[1], quad[2], quad[3]);
+    }
+}
+
+#[wasm_bindgen(js_class="ForestOfIdentifierQuads")]
+impl ForestOfIdentifierQuads {
+    /// Returns, for every quad of the identifier list, 1 if it is in the
+    /// dataset and 0 if it is not.
+    ///
+    /// Panics if the length of the list is not a multiple of 4.
+    #[wasm_bindgen(js_name = hasEach)]
+    pub fn has_each(&self, encoded_quads: &[u32]) -> Box<[u8]> {
+        assert!(encoded_quads.len() % 4 == 0);
+
+        encoded_quads
+            .chunks_exact(4)
+            .map(|quad| self.has(quad[0], quad[1], quad[2], quad[3]) as u8)
+            .collect()
+    }
+}
+
+#[wasm_bindgen(js_class="ForestOfIdentifierQuads")]
+impl ForestOfIdentifierQuads {
+    /// Returns true if the triple is in at least one graph
+    #[wasm_bindgen(js_name = hasTripleAnyGraph)]
+    pub fn has_triple_any_graph(&self, s: u32, p: u32, o: u32) -> bool {
+        self.matching_quads([Some(s), Some(p), Some(o), None]).next().is_some()
+    }
+}
+
 #[wasm_bindgen(js_class="ForestOfIdentifierQuads")]
 impl ForestOfIdentifierQuads {
     /// Returns a slice with every quad flattened
@@ -66,9 +350,24 @@ impl ForestOfIdentifierQuads {
         // 1- wasm bindgen has a memory friendly way to return this data structure (no memory leak)
         // 2- memcpy-ing is stupidly fast
 
-        let mut vector = vec!();
+        let spog = [s, p, o, g];
+        let quads = self.matching_quads(spog);
+
+        // Reserve once when the number of quads is known: every quad matches
+        // a pattern without fixed terms, and the range of the frozen list only
+        // contains matching quads if the fixed terms are a prefix of SPOG.
+        // The trees give no bound.
+        let is_spog_prefix = spog.iter().skip_while(|term| term.is_some()).all(|term| term.is_none());
+        let number_of_quads = if spog == [None, None, None, None] {
+            self.size()
+        } else if self.frozen.is_some() && is_spog_prefix {
+            quads.size_hint().1.unwrap_or(0)
+        } else {
+            0
+        };
+        let mut vector = Vec::with_capacity(number_of_quads * 4);
 
-        for quad in self.trees.filter([s, p, o, g]) {
+        for quad in quads {
             vector.push(quad[0]);
             vector.push(quad[1]);
             vector.push(quad[2]);
@@ -79,16 +378,207 @@ impl ForestOfIdentifierQuads {
     }
 }
 
+#[wasm_bindgen(js_class="ForestOfIdentifierQuads")]
+impl ForestOfIdentifierQuads {
+    /// Returns a slice with every quad flattened, sorted by subject, then
+    /// predicate, then object and then graph.
+    ///
+    /// Unlike `get_all`, the order does not depend on the trees that are
+    /// built, so two datasets with the same quads always give the same list.
+    #[wasm_bindgen(js_name = getAllCanonical)]
+    pub fn get_all_canonical(&self) -> Box<[u32]> {
+        self.sorted_matching_quads([None, None, None, None], SPOG)
+            .iter()
+            .flatten()
+            .copied()
+            .collect()
+    }
+}
+
+#[wasm_bindgen(js_class="ForestOfIdentifierQuads")]
+impl ForestOfIdentifierQuads {
+    /// Returns a slice with every quad flattened, in the order of the base
+    /// tree (OGPS).
+    ///
+    /// `get_all` uses the order of the tree chosen to answer the query, which
+    /// may change when optional trees are built. This order never changes.
+    #[wasm_bindgen(js_name = getAllInBaseOrder)]
+    pub fn get_all_in_base_order(&self) -> Box<[u32]> {
+        self.sorted_matching_quads([None, None, None, None], self.base_order())
+            .iter()
+            .flatten()
+            .copied()
+            .collect()
+    }
+}
+
+/// A page of the quads that match a pattern, see `getAllWithCount`
+#[wasm_bindgen]
+pub struct MatchPage {
+    /// Number of quads that match the pattern, in every page
+    total: usize,
+    /// The quads of the page, flattened
+    quads: Box<[u32]>
+}
+
+#[wasm_bindgen]
+impl MatchPage {
+    /// Returns the number of quads that match the pattern, including the ones
+    /// that are not in this page
+    #[wasm_bindgen(getter)]
+    pub fn total(&self) -> usize {
+        self.total
+    }
+
+    /// Returns the quads of the page, flattened
+    #[wasm_bindgen(getter)]
+    pub fn quads(&self) -> Box<[u32]> {
+        self.quads.clone()
+    }
+}
+
+#[wasm_bindgen(js_class="ForestOfIdentifierQuads")]
+impl ForestOfIdentifierQuads {
+    /// Returns the number of quads that match the pattern and, flattened, at
+    /// most `limit` of them, starting from the `offset`-th one in the order of
+    /// `get_all`.
+    ///
+    /// The matching quads are only read once. If the pattern has no fixed
+    /// term, the total is the size of the dataset and the quads after the page
+    /// are not read.
+    #[wasm_bindgen(js_name = getAllWithCount)]
+    pub fn get_all_with_count(&self, s: Option<u32>, p: Option<u32>, o: Option<u32>, g: Option<u32>,
+        offset: usize, limit: usize) -> MatchPage {
+        let every_quad_matches = s.is_none() && p.is_none() && o.is_none() && g.is_none();
+        let end = offset.saturating_add(limit);
+
+        let mut number_of_read_quads = 0;
+        let mut page = vec!();
+
+        for quad in self.matching_quads([s, p, o, g]) {
+            if number_of_read_quads >= end && every_quad_matches {
+                break;
+            }
+
+            if number_of_read_quads >= offset && number_of_read_quads < end {
+                page.extend_from_slice(&quad);
+            }
+
+            number_of_read_quads += 1;
+        }
+
+        let total = if every_quad_matches { self.size() } else { number_of_read_quads };
+
+        MatchPage { total, quads: page.into_boxed_slice() }
+    }
+}
+
+impl ForestOfIdentifierQuads {
+    /// Returns an iterator on every quad, for the Rust crates that use this
+    /// one directly.
+    ///
+    /// Unlike `get_all`, no list is allocated. The quads are returned in the
+    /// order of the base tree (OGPS), or in SPOG order if the dataset is
+    /// frozen.
+    pub fn iter_quads(&self) -> impl Iterator<Item=[u32; 4]> + '_ {
+        self.matching_quads([None, None, None, None])
+    }
+}
+
+#[wasm_bindgen(js_class="ForestOfIdentifierQuads")]
+impl ForestOfIdentifierQuads {
+    /// Returns an array with one `Uint32Array` of 4 identifiers per quad that
+    /// matches the given pattern.
+    ///
+    /// This is easier to use than the flat list of `get_all`, but much slower
+    /// as one Javascript object is created per quad.
+    #[wasm_bindgen(js_name = toQuadArray)]
+    pub fn to_quad_array(&self, s: Option<u32>, p: Option<u32>, o: Option<u32>, g: Option<u32>) -> js_sys::Array {
+        self.matching_quads([s, p, o, g])
+            .map(|quad| js_sys::Uint32Array::from(&quad[..]))
+            .collect()
+    }
+}
+
+#[wasm_bindgen(js_class="ForestOfIdentifierQuads")]
+impl ForestOfIdentifierQuads {
+    /// Calls `callback` with a `Uint32Array` of 4 identifiers for every quad
+    /// that matches the given pattern, without building the list of `get_all`.
+    ///
+    /// If the callback throws, the iteration stops and the exception is
+    /// thrown again.
+    #[wasm_bindgen(js_name = forEachMatching)]
+    pub fn for_each_matching(&self, s: Option<u32>, p: Option<u32>, o: Option<u32>, g: Option<u32>,
+        callback: &js_sys::Function) -> Result<(), JsValue> {
+        for quad in self.matching_quads([s, p, o, g]) {
+            callback.call1(&JsValue::NULL, &js_sys::Uint32Array::from(&quad[..]))?;
+        }
+
+        Ok(())
+    }
+}
+
+impl ForestOfIdentifierQuads {
+    /// Writes the quads that match the given pattern, flattened, in `out` and
+    /// returns the number of written quads. At most `out.len() / 4` quads are
+    /// written.
+    pub fn write_all_into(&self, s: Option<u32>, p: Option<u32>, o: Option<u32>, g: Option<u32>,
+        out: &mut [u32]) -> usize {
+        let mut written_quads = 0;
+        for (quad, destination) in self.matching_quads([s, p, o, g]).zip(out.chunks_exact_mut(4)) {
+            destination.copy_from_slice(&quad);
+            written_quads += 1;
+        }
+
+        written_quads
+    }
+}
+
+/// Writes the quads of `forest` that match the given pattern, flattened, in
+/// the `capacity` integers that start at `out_ptr` in the wasm memory, and
+/// returns the number of written quads, see `write_all_into`.
+///
+/// At most `capacity / 4` quads are written. The region must be owned by the
+/// caller, must not overlap the memory of the dataset and must stay allocated
+/// during the call. Views on the wasm memory are invalidated when the memory
+/// grows, so the `Uint32Array` view on the region has to be built again after
+/// any call that may allocate.
+///
+/// It is called as `writeAllInto(dataset, s, p, o, g, outPtr, capacity)`. As
+/// the pointer can not be checked, this is not a method: it is a function of
+/// the private `quadforest` module that can only be called from JavaScript,
+/// and Rust code uses the safe `write_all_into` method instead.
+#[wasm_bindgen(js_name = writeAllInto)]
+pub fn write_all_into_memory(forest: &ForestOfIdentifierQuads,
+    s: Option<u32>, p: Option<u32>, o: Option<u32>, g: Option<u32>,
+    out_ptr: *mut u32, capacity: usize) -> usize {
+    if capacity < 4 || out_ptr.is_null() {
+        return 0;
+    }
+
+    // Safety: the caller guarantees that the region is valid and is not
+    // aliased by anything we read
+    let out = unsafe { std::slice::from_raw_parts_mut(out_ptr, capacity) };
+    forest.write_all_into(s, p, o, g, out)
+}
+
 #[wasm_bindgen(js_class="ForestOfIdentifierQuads")]
 impl ForestOfIdentifierQuads {
     /// Builds a new dataset which is built by filtering with the given s, p, o and g.
     pub fn new_from(&self, s: Option<u32>, p: Option<u32>, o: Option<u32>, g: Option<u32>) -> Self {
-        let mut new_tree = ForestOfIdentifierQuads::new();
-        self.trees
-            .filter([s, p, o, g])
+        let mut new_tree = self.new_like();
+        self.matching_quads([s, p, o, g])
             .for_each(|quad| { new_tree.trees.insert(quad); } );
         new_tree
     }
+
+    /// Builds a new dataset with the quads that match the given pattern.
+    ///
+    /// This is `new_from` with the name of the RDF.JS DatasetCore interface.
+    #[wasm_bindgen(js_name = match)]
+    pub fn match_pattern(&self, s: Option<u32>, p: Option<u32>, o: Option<u32>, g: Option<u32>) -> Self {
+        self.new_from(s, p, o, g)
+    }
 }
 
 #[wasm_bindgen(js_class="ForestOfIdentifierQuads")]
@@ -110,6 +600,36 @@ impl ForestOfIdentifierQuads {
         }
     }
 
+    /// Insert every quads described in the passed identifier list, like
+    /// `insertFromIdentifierList`, and returns the number of quads that were
+    /// not already in the dataset.
+    ///
+    /// A quad that appears several times in the list is counted once.
+    #[wasm_bindgen(js_name = extendFromSlice)]
+    pub fn extend_from_slice(&mut self, encoded_quads: &[u32]) -> usize {
+        encoded_quads
+            .chunks_exact(4)
+            .filter(|quad| self.insert_quad([quad[0], quad[1], quad[2], quad[3]]))
+            .count()
+    }
+
+    /// Insert every quads described in the passed identifier list, like
+    /// `insertFromIdentifierList`, and returns, flattened, the quads that were
+    /// already in the dataset and have not been added.
+    ///
+    /// If a quad appears several times in the list, its first occurrence
+    /// inserts it, so every other occurrence is returned. The quads rejected
+    /// because of `setStrictSentinel` are returned too.
+    #[wasm_bindgen(js_name = addIfAbsentFromIdentifierList)]
+    pub fn add_if_absent(&mut self, encoded_quads: &[u32]) -> Box<[u32]> {
+        encoded_quads
+            .chunks_exact(4)
+            .filter(|quad| !self.insert_quad([quad[0], quad[1], quad[2], quad[3]]))
+            .flatten()
+            .copied()
+            .collect()
+    }
+
     /// Builds a ForestOfIdentifierQuads from an identifier list of quads
     /// 
     /// If you have previously extracted an identifier list from get_all, you
@@ -136,246 +656,3151 @@ impl ForestOfIdentifierQuads {
     }
 }
 
-#[wasm_bindgen(js_class="ForestOfIdentifierQuads")]
-impl ForestOfIdentifierQuads {
-    /// Removes from the dataset the quads that matches the given pattern
-    #[wasm_bindgen(js_name = deleteMatches)]
-    pub fn delete_matches(&mut self, s: Option<u32>, p: Option<u32>, o: Option<u32>, g: Option<u32>) {
-        // 1- Find quads that matches
-        let quads = self.trees.search_all_matching_quads([s, p, o, g], false).collect::<Vec<[u32; 4]>>();
+// ==== Tree selection and usage tracking
 
-        let ratio_threshhold = 2 + self.number_of_optional_built_trees();
+/// Returns the position in a `[s, p, o, g]` quad of the term with the given
+/// role
+fn term_role_position(term_role: &TermRole) -> usize {
+    match term_role {
+        TermRole::Subject   => 0,
+        TermRole::Predicate => 1,
+        TermRole::Object    => 2,
+        TermRole::Graph     => 3
+    }
+}
 
-        if quads.len() < self.size() / ratio_threshhold {
-            // 2- Remove quads if there are not a lot to remove
+/// Returns the number of leading terms of a tree sorted with the given term
+/// roles that are fixed by the pattern, i.e. the number of terms that narrow
+/// the range of quads to explore in this tree.
+///
+/// The count stops at the first term that the pattern does not fix: a fixed
+/// term that comes after it in the order does not narrow the range, it is only
+/// checked on every quad of the range. For example, a pattern that fixes the
+/// subject and the object has a prefix of 1 in an OGPS tree.
+fn range_prefix_len(term_roles: &[TermRole], spog: &[Option<u32>; 4]) -> usize {
+    term_roles
+        .iter()
+        .take_while(|term_role| spog[term_role_position(term_role)].is_some())
+        .count()
+}
 
-            for quad in quads {
-                self.trees.delete(quad);
-            }
-        } else {
-            // 2- If there are a lot, rebuild tree
-            let spog = [s, p, o, g];
+/// Returns true if the quad matches the pattern
+fn matches_pattern(quad: &[u32; 4], spog: &[Option<u32>; 4]) -> bool {
+    quad.iter()
+        .zip(spog.iter())
+        .all(|(term, pattern)| match pattern {
+            Some(value) => value == term,
+            None => true
+        })
+}
 
-            // Delete every secondary tree. We do this first to let the new tree
-            // eventually reuse the allocated memory of the former trees
-            for optional_tree_tuple in self.trees.optional_trees.iter_mut() {
-                optional_tree_tuple.1.take();
-            }
-    
-            // Build the new filtered tree and replace the old one
-            let new_tree = self.trees.base_tree.0.filter_to_tree(&self.trees.base_tree.1, &spog);
-            self.trees.base_tree.1 = new_tree;
-        }
-    }
+/// Returns a function that tells if a quad matches the pattern
+#[cfg(not(all(feature = "simd", target_arch = "wasm32", target_feature = "simd128")))]
+fn pattern_matcher(spog: [Option<u32>; 4]) -> impl Fn(&[u32; 4]) -> bool {
+    move |quad| matches_pattern(quad, &spog)
 }
 
-impl ForestOfIdentifierQuads {
-    pub fn are_trivially_mergeable_trees(lhs: &Self, rhs: &Self) -> bool {
-        // TODO : static_assert
-        // TODO : be able to merge indexes that are not the first from new
-        let expected_block = [TermRole::Object, TermRole::Graph, TermRole::Predicate, TermRole::Subject];
-        lhs.trees.base_tree.0.get_term_roles() == &expected_block && rhs.trees.base_tree.0.get_term_roles() == &expected_block
+/// Returns a function that tells if a quad matches the pattern, by comparing
+/// the 4 terms at once with SIMD instructions
+#[cfg(all(feature = "simd", target_arch = "wasm32", target_feature = "simd128"))]
+fn pattern_matcher(spog: [Option<u32>; 4]) -> impl Fn(&[u32; 4]) -> bool {
+    use core::arch::wasm32::{u32x4, u32x4_all_true, u32x4_eq, v128_or};
+
+    let value = |i: usize| spog[i].unwrap_or(0);
+    let wildcard = |i: usize| if spog[i].is_none() { u32::MAX } else { 0 };
+
+    let values = u32x4(value(0), value(1), value(2), value(3));
+    let wildcards = u32x4(wildcard(0), wildcard(1), wildcard(2), wildcard(3));
+
+    move |quad| {
+        let quad = u32x4(quad[0], quad[1], quad[2], quad[3]);
+        u32x4_all_true(v128_or(u32x4_eq(quad, values), wildcards))
     }
+}
 
-    fn new_tree_from_fusion<'a, BlockIterator>(iterator: BlockIterator) -> Self
-        where BlockIterator: Iterator<Item=&'a Block<u32>> {
-        let mut new_tree = Self::new();
-        new_tree.trees.base_tree.1.extend(iterator);
-        new_tree
+/// Returns the range of a list sorted in SPOG order in which the quads have
+/// the leading fixed terms of the pattern, found by binary search
+fn sorted_list_range<'a>(quads: &'a [[u32; 4]], spog: &[Option<u32>; 4]) -> &'a [[u32; 4]] {
+    let prefix_len = spog.iter().take_while(|term| term.is_some()).count();
+    let mut prefix = [0; 4];
+    for (value, term) in prefix.iter_mut().zip(spog.iter()).take(prefix_len) {
+        *value = term.unwrap();
     }
+
+    let start = quads.partition_point(|quad| quad[0..prefix_len] < prefix[0..prefix_len]);
+    let end = quads.partition_point(|quad| quad[0..prefix_len] <= prefix[0..prefix_len]);
+
+    &quads[start..end]
 }
 
-#[wasm_bindgen(js_class="ForestOfIdentifierQuads")]
-impl ForestOfIdentifierQuads {
-    #[wasm_bindgen]
-    pub fn insersect(&self, other: &ForestOfIdentifierQuads) -> ForestOfIdentifierQuads {
-        if ForestOfIdentifierQuads::are_trivially_mergeable_trees(self, other) {
-            Self::new_tree_from_fusion(self.trees.base_tree.1.intersection(&other.trees.base_tree.1))
-        } else {
-            let mut new_tree = Self::new();
+/// Returns the quads of a list sorted in SPOG order that match the pattern.
+///
+/// The leading fixed terms of the pattern are found by binary search, the
+/// other ones are filtered. With the `simd` feature, the filter uses the SIMD
+/// instructions of wasm32.
+fn sorted_list_matching_quads<'a>(quads: &'a [[u32; 4]], spog: [Option<u32>; 4]) -> impl Iterator<Item=[u32; 4]> + 'a {
+    sorted_list_range(quads, &spog)
+        .iter()
+        .copied()
+        .filter(pattern_matcher(spog))
+}
 
-            for quad in self.trees.filter([None, None, None, None]) {
-                if other.has(quad[0], quad[1], quad[2], quad[3]) {
-                    new_tree.add(quad[0], quad[1], quad[2], quad[3]);
-                }
-            }
+/// Iterator on the quads found either in the frozen list or in the trees
+enum MatchingQuads<F, T> {
+    Frozen(F),
+    Trees(T)
+}
+
+impl<F, T> Iterator for MatchingQuads<F, T>
+    where F: Iterator<Item=[u32; 4]>, T: Iterator<Item=[u32; 4]> {
+    type Item = [u32; 4];
+
+    fn next(&mut self) -> Option<[u32; 4]> {
+        match self {
+            MatchingQuads::Frozen(quads) => quads.next(),
+            MatchingQuads::Trees(quads) => quads.next()
+        }
+    }
 
-            new_tree
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        match self {
+            MatchingQuads::Frozen(quads) => quads.size_hint(),
+            MatchingQuads::Trees(quads) => quads.size_hint()
         }
     }
 }
 
-#[wasm_bindgen(js_class="ForestOfIdentifierQuads")]
 impl ForestOfIdentifierQuads {
-    #[wasm_bindgen(js_name = intersectIdentifierList)]
-    pub fn intersect_slice(&self, other: &[u32]) -> Self {
-        let mut new_tree = Self::new();
-
-        for i in 0..other.len() / 4 {
-            if self.has(other[i * 4], other[i * 4 + 1], other[i * 4 + 2], other[i * 4 + 3]) {
-                new_tree.add(other[i * 4], other[i * 4 + 1], other[i * 4 + 2], other[i * 4 + 3]);
-            }
+    /// Returns the quads that match the given pattern.
+    ///
+    /// A new optional tree is only built if the indexes are not locked and the
+    /// maximum number of living trees allows it. If the dataset is frozen, the trees are not used.
+    ///
+    /// Queries only borrow the dataset, and dropping a tree requires to
+    /// borrow it mutably, so no tree is evicted here: once the maximum is
+    /// reached, the living trees answer the query.
+    fn matching_quads(&self, spog: [Option<u32>; 4]) -> impl Iterator<Item=[u32; 4]> + '_ {
+        if let Some(frozen) = self.frozen.as_ref() {
+            return MatchingQuads::Frozen(sorted_list_matching_quads(frozen, spog));
         }
 
-        new_tree
-    }
+        let can_build_new_tree = !self.indexes_locked
+            && 1 + self.number_of_optional_built_trees() < self.max_living_trees;
+        let quads = self.trees.search_all_matching_quads(spog, can_build_new_tree);
+        self.count_query(&spog);
 
-    #[wasm_bindgen(js_name = unionIdentifierList)]
-    pub fn union_slice(&self, other: &[u32]) -> Self {
-        let mut new_tree = Self::new();
+        MatchingQuads::Trees(quads)
+    }
 
-        for quad in self.trees.filter([None, None, None, None]) {
-            new_tree.add(quad[0], quad[1], quad[2], quad[3]);
-        }
+    /// Marks the tree that answers a search in the trees with this pattern as
+    /// the most recently used, and increments its count of queries.
+    ///
+    /// The tree is the one chosen by `fitting_optional_tree`, which mirrors
+    /// the choice of `search_all_matching_quads` but is not told which tree it
+    /// really used: when two trees fit the pattern equally, the counts may be
+    /// given to the other one.
+    fn count_query(&self, spog: &[Option<u32>; 4]) {
+        let query_count = match self.record_use(spog) {
+            None => &self.query_counts[0],
+            Some(i) => &self.query_counts[i + 1]
+        };
+        query_count.set(query_count.get() + 1);
+    }
 
-        for i in 0..other.len() / 4 {
-            new_tree.add(other[i * 4], other[i * 4 + 1], other[i * 4 + 2], other[i * 4 + 3]);
-        }
+    /// Returns the index of the built optional tree that fits best the
+    /// pattern, or `None` if no optional tree fits better than the base tree
+    fn fitting_optional_tree(&self, spog: &[Option<u32>; 4]) -> Option<usize> {
+        let base_prefix_len = range_prefix_len(self.trees.base_tree.0.get_term_roles(), spog);
 
-        new_tree
+        self.trees
+            .optional_trees
+            .iter()
+            .enumerate()
+            .filter(|(_, pair)| pair.1.get().is_some())
+            .map(|(i, pair)| (i, range_prefix_len(pair.0.get_term_roles(), spog)))
+            .filter(|(_, prefix_len)| *prefix_len > base_prefix_len)
+            .max_by_key(|(_, prefix_len)| *prefix_len)
+            .map(|(i, _)| i)
     }
 
-    #[wasm_bindgen(js_name = differenceIdentifierList)]
-    pub fn difference_slice(&self, other: &[u32]) -> Self {
-        let mut new_tree = Self::new();
-
-        for quad in self.trees.filter([None, None, None, None]) {
-            new_tree.add(quad[0], quad[1], quad[2], quad[3]);
-        }
+    /// Marks the tree that fits best the pattern as the most recently used,
+    /// and returns its index like `fitting_optional_tree`
+    fn record_use(&self, spog: &[Option<u32>; 4]) -> Option<usize> {
+        let now = self.clock.get() + 1;
+        self.clock.set(now);
 
-        for i in 0..other.len() / 4 {
-            new_tree.trees.delete([other[i * 4], other[i * 4 + 1], other[i * 4 + 2], other[i * 4 + 3]]);
+        let fitting_tree = self.fitting_optional_tree(spog);
+        if let Some(i) = fitting_tree {
+            self.last_uses[i].set(now);
         }
 
-        new_tree
+        fitting_tree
     }
-}
 
-#[wasm_bindgen(js_class="ForestOfIdentifierQuads")]
+    /// Drops the least recently used optional trees until the number of living
+    /// trees is lower or equal to the maximum
+    fn evict_least_recently_used_trees(&mut self) {
+        while 1 + self.number_of_optional_built_trees() > self.max_living_trees {
+            let last_uses = &self.last_uses;
+            let least_recently_used = self.trees
+                .optional_trees
+                .iter()
+                .enumerate()
+                .filter(|(_, pair)| pair.1.get().is_some())
+                .min_by_key(|(i, _)| last_uses[*i].get())
+                .map(|(i, _)| i);
+
+            match least_recently_used {
+                Some(i) => { self.trees.optional_trees[i].1.take(); },
+                None => break
+            }
+        }
+    }
+}
+
+#[wasm_bindgen(js_class="ForestOfIdentifierQuads")]
+impl ForestOfIdentifierQuads {
+    /// Sets the maximum number of trees, including the base tree, that can be
+    /// alive at the same time.
+    ///
+    /// The least recently used optional trees are dropped to respect the new
+    /// maximum. The eviction only happens here and in `ensureHasIndexfor`,
+    /// which drops the least recently used optional tree to make room for the
+    /// tree it builds. Queries can not drop a tree as they do not modify the
+    /// dataset: when the maximum is reached, they never build a new tree and
+    /// use the living trees instead, so call `ensureHasIndexfor` to replace a
+    /// tree with the one a pattern needs. The base tree is never dropped, so a
+    /// maximum of 0 behaves like 1.
+    #[wasm_bindgen(js_name = setMaxLivingTrees)]
+    pub fn set_max_living_trees(&mut self, max: usize) {
+        self.max_living_trees = max.max(1);
+        self.evict_least_recently_used_trees();
+    }
+}
+
+#[wasm_bindgen(js_class="ForestOfIdentifierQuads")]
+impl ForestOfIdentifierQuads {
+    /// Locks or unlocks the set of built trees. While it is locked, queries
+    /// only use the living trees and never build an optional tree, even if it
+    /// would fit the pattern better: they may be slower, but their time and
+    /// memory usage are predictable.
+    ///
+    /// `ensureHasIndexfor` still builds the requested tree.
+    #[wasm_bindgen(js_name = lockIndexes)]
+    pub fn lock_indexes(&mut self, locked: bool) {
+        self.indexes_locked = locked;
+    }
+}
+
+#[wasm_bindgen(js_class="ForestOfIdentifierQuads")]
+impl ForestOfIdentifierQuads {
+    /// Returns the number of fixed terms of a pattern that narrow the range of
+    /// quads to explore, in the living tree that fits best a pattern in which
+    /// the terms for which true is passed are fixed.
+    ///
+    /// The other fixed terms are checked on every quad of the range, so the
+    /// greater the result, the fewer quads are read. No tree is built.
+    #[wasm_bindgen(js_name = rangePrefixLength)]
+    pub fn range_prefix_length(&self, s: bool, p: bool, o: bool, g: bool) -> usize {
+        let as_pattern = |is_fixed: bool| if is_fixed { Some(0) } else { None };
+        let spog = [as_pattern(s), as_pattern(p), as_pattern(o), as_pattern(g)];
+
+        if self.frozen.is_some() {
+            return spog.iter().take_while(|term| term.is_some()).count();
+        }
+
+        let base_prefix_len = range_prefix_len(self.trees.base_tree.0.get_term_roles(), &spog);
+
+        match self.fitting_optional_tree(&spog) {
+            None => base_prefix_len,
+            Some(i) => range_prefix_len(self.trees.optional_trees[i].0.get_term_roles(), &spog)
+        }
+    }
+}
+
+/// Term positions of a quad sorted by subject, predicate, object then graph
+const SPOG: [usize; 4] = [0, 1, 2, 3];
+
+impl ForestOfIdentifierQuads {
+    /// Returns the quads that match the pattern, sorted with the given order
+    /// of term positions.
+    ///
+    /// The quads are only sorted if they are not already read in this order:
+    /// the quads of a frozen dataset are read from its list sorted in SPOG
+    /// order, and a full scan of the other datasets reads the base tree.
+    fn sorted_matching_quads(&self, spog: [Option<u32>; 4], order: [usize; 4]) -> Vec<[u32; 4]> {
+        let mut quads = self.matching_quads(spog).collect::<Vec<[u32; 4]>>();
+
+        let read_in_scan_order = self.frozen.is_some() || spog == [None, None, None, None];
+        if !read_in_scan_order || !self.scan_is_sorted_by(&order) {
+            quads.sort_unstable_by_key(|quad| [quad[order[0]], quad[order[1]], quad[order[2]], quad[order[3]]]);
+        }
+
+        quads
+    }
+}
+
+/// Calls `f` with each key of the quads and the number of quads that have it,
+/// in increasing key order.
+///
+/// If the quads are sorted by key, the keys are counted while the quads are
+/// read and only one count is held. Else, they are counted in a map with one
+/// entry per key.
+fn count_by_key<K, Q, F, G>(quads: Q, sorted: bool, key: F, mut f: G)
+    where K: Ord, Q: Iterator<Item=[u32; 4]>, F: Fn(&[u32; 4]) -> K, G: FnMut(K, u32) {
+    if !sorted {
+        let mut counts = BTreeMap::new();
+        for quad in quads {
+            *counts.entry(key(&quad)).or_insert(0) += 1;
+        }
+
+        for (quad_key, count) in counts {
+            f(quad_key, count);
+        }
+
+        return;
+    }
+
+    let mut current: Option<(K, u32)> = None;
+
+    for quad in quads {
+        let quad_key = key(&quad);
+
+        match current.as_mut() {
+            Some((current_key, count)) if *current_key == quad_key => *count += 1,
+            _ => {
+                if let Some((previous_key, count)) = current.replace((quad_key, 1)) {
+                    f(previous_key, count);
+                }
+            }
+        }
+    }
+
+    if let Some((last_key, count)) = current {
+        f(last_key, count);
+    }
+}
+
+impl ForestOfIdentifierQuads {
+    /// Returns the positions of the terms in the order of the base tree
+    fn base_order(&self) -> [usize; 4] {
+        let term_roles = self.trees.base_tree.0.get_term_roles();
+        [
+            term_role_position(&term_roles[0]),
+            term_role_position(&term_roles[1]),
+            term_role_position(&term_roles[2]),
+            term_role_position(&term_roles[3])
+        ]
+    }
+
+    /// Returns true if a full scan returns the quads sorted by the terms at
+    /// `positions`, in this order. A full scan reads the base tree, or the
+    /// list sorted in SPOG order if the dataset is frozen.
+    fn scan_is_sorted_by(&self, positions: &[usize]) -> bool {
+        let scan_order = if self.frozen.is_some() { SPOG } else { self.base_order() };
+        scan_order.starts_with(positions)
+    }
+
+    /// Returns the quads of the dataset sorted in the order of the base tree.
+    /// They are read from the base tree, or sorted again if the dataset is
+    /// frozen.
+    fn base_order_quads(&self) -> impl Iterator<Item=[u32; 4]> + '_ {
+        match self.frozen {
+            Some(_) => MatchingQuads::Frozen(self.sorted_matching_quads([None, None, None, None], self.base_order()).into_iter()),
+            None => MatchingQuads::Trees(self.matching_quads([None, None, None, None]))
+        }
+    }
+}
+
+impl ForestOfIdentifierQuads {
+    /// Returns the indexes of the built optional trees
+    fn built_optional_trees(&self) -> impl Iterator<Item=usize> + '_ {
+        self.trees
+            .optional_trees
+            .iter()
+            .enumerate()
+            .filter(|(_, pair)| pair.1.get().is_some())
+            .map(|(i, _)| i)
+    }
+}
+
+#[wasm_bindgen(js_class="ForestOfIdentifierQuads")]
+impl ForestOfIdentifierQuads {
+    /// Returns the order of every built tree, the base tree first.
+    ///
+    /// Each order is described by 4 integers, the positions of the sorted
+    /// terms in a quad (0 for the subject, 1 for the predicate, 2 for the
+    /// object and 3 for the graph). For example, OGPS is `[2, 3, 1, 0]`.
+    #[wasm_bindgen(js_name = builtIndexOrders)]
+    pub fn built_index_orders(&self) -> Box<[u8]> {
+        std::iter::once(self.trees.base_tree.0.get_term_roles())
+            .chain(self.built_optional_trees().map(|i| self.trees.optional_trees[i].0.get_term_roles()))
+            .flat_map(|term_roles| term_roles.iter().map(|term_role| term_role_position(term_role) as u8))
+            .collect()
+    }
+}
+
+#[wasm_bindgen(js_class="ForestOfIdentifierQuads")]
+impl ForestOfIdentifierQuads {
+    /// Returns, for every built tree, the number of queries it has answered.
+    ///
+    /// The counts are aligned with `builtIndexOrders`. They include the
+    /// searches made by the functions that modify the dataset, like
+    /// `deleteMatches`. They are approximate: identifier_forest does not tell
+    /// which tree answered a query, so when two trees fit a pattern as well,
+    /// the query may be counted for the one it did not use.
+    #[wasm_bindgen(js_name = indexQueryCounts)]
+    pub fn index_query_counts(&self) -> Box<[u32]> {
+        std::iter::once(0)
+            .chain(self.built_optional_trees().map(|i| i + 1))
+            .map(|i| self.query_counts[i].get())
+            .collect()
+    }
+}
+
+#[wasm_bindgen(js_class="ForestOfIdentifierQuads")]
+impl ForestOfIdentifierQuads {
+    /// Returns, for every built tree, the number of quads it contains.
+    ///
+    /// The sizes are aligned with `builtIndexOrders`. Every tree contains the
+    /// same quads, so they should all be equal to `size`.
+    #[wasm_bindgen(js_name = indexSizes)]
+    pub fn index_sizes(&self) -> Box<[u32]> {
+        let sizes = std::iter::once(self.trees.base_tree.1.len())
+            .chain(self.built_optional_trees().map(|i| self.trees.optional_trees[i].1.get().unwrap().len()))
+            .map(|size| size as u32)
+            .collect::<Box<[u32]>>();
+
+        debug_assert!(sizes.iter().all(|size| *size as usize == self.size()));
+        sizes
+    }
+}
+
+#[wasm_bindgen(js_class="ForestOfIdentifierQuads")]
+impl ForestOfIdentifierQuads {
+    /// Removes from the dataset the quads that matches the given pattern
+    #[wasm_bindgen(js_name = deleteMatches)]
+    pub fn delete_matches(&mut self, s: Option<u32>, p: Option<u32>, o: Option<u32>, g: Option<u32>) {
+        let ratio_threshhold = 2 + self.number_of_optional_built_trees();
+        self.delete_matches_with_threshold([s, p, o, g], ratio_threshhold);
+    }
+}
+
+impl ForestOfIdentifierQuads {
+    /// Removes from the dataset the quads that matches the given pattern and
+    /// returns the number of removed quads.
+    ///
+    /// If less than `size / ratio_threshhold` quads have to be removed, they
+    /// are removed one by one. Else, the trees are rebuilt.
+    fn delete_matches_with_threshold(&mut self, spog: [Option<u32>; 4], ratio_threshhold: usize) -> usize {
+        // 1- Find quads that matches
+        let quads = self.trees.search_all_matching_quads(spog, false).collect::<Vec<[u32; 4]>>();
+        self.count_query(&spog);
+        let number_of_removed_quads = quads.len();
+
+        if quads.len() < self.size() / ratio_threshhold {
+            // 2- Remove quads if there are not a lot to remove
+
+            for quad in quads {
+                self.delete_quad(quad);
+            }
+        } else {
+            // 2- If there are a lot, rebuild tree
+
+            // Delete every secondary tree. We do this first to let the new tree
+            // eventually reuse the allocated memory of the former trees
+            for optional_tree_tuple in self.trees.optional_trees.iter_mut() {
+                optional_tree_tuple.1.take();
+            }
+    
+            // Build the new filtered tree and replace the old one
+            let new_tree = self.trees.base_tree.0.filter_to_tree(&self.trees.base_tree.1, &spog);
+            self.trees.base_tree.1 = new_tree;
+
+            for quad in quads {
+                self.record_change(quad, true);
+            }
+        }
+
+        number_of_removed_quads
+    }
+}
+
+#[wasm_bindgen(js_class="ForestOfIdentifierQuads")]
+impl ForestOfIdentifierQuads {
+    /// Removes from the dataset the quads that match none of the patterns.
+    ///
+    /// `patterns` is a list of groups of 4 values, written like in
+    /// `matchBindings`: -1 for the positions that can be any term. The quads
+    /// to keep are collected first and the trees are rebuilt with them, so
+    /// the optional trees are dropped.
+    ///
+    /// An error is thrown, and the dataset is left untouched, if a pattern
+    /// contains a negative value other than -1.
+    #[wasm_bindgen(js_name = retainMatchingAny)]
+    pub fn retain_matching_any(&mut self, patterns: &[i32]) -> Result<(), JsValue> {
+        assert!(patterns.len() % 4 == 0);
+
+        let patterns = patterns
+            .chunks(4)
+            .map(|pattern| to_spog_pattern([pattern[0], pattern[1], pattern[2], pattern[3]]))
+            .collect::<Result<Vec<[Option<u32>; 4]>, JsValue>>()?;
+
+        let mut kept_quads = BTreeSet::new();
+        for spog in patterns {
+            kept_quads.extend(self.trees.search_all_matching_quads(spog, false));
+            self.count_query(&spog);
+        }
+
+        if kept_quads.len() == self.size() {
+            return Ok(());
+        }
+
+        let removed_quads = self.trees.search_all_matching_quads([None, None, None, None], false)
+            .filter(|quad| !kept_quads.contains(quad))
+            .collect::<Vec<[u32; 4]>>();
+        self.count_query(&[None, None, None, None]);
+
+        // A new forest only has its base tree
+        let mut trees = IndexingForest4::new();
+        for quad in kept_quads {
+            trees.insert(quad);
+        }
+        self.trees = trees;
+
+        for quad in removed_quads {
+            self.record_change(quad, true);
+        }
+
+        Ok(())
+    }
+}
+
+impl ForestOfIdentifierQuads {
+    /// Returns the base trees of the two datasets.
+    ///
+    /// Every dataset is built with an OGPS base tree, so the base trees of two
+    /// datasets can always be merged by walking through them simultaneously.
+    /// Panics if the base orders differ, as merging them would produce wrong
+    /// results.
+    fn base_trees<'a>(lhs: &'a Self, rhs: &'a Self) -> (&'a BTreeSet<Block<u32>>, &'a BTreeSet<Block<u32>>) {
+        assert!(
+            lhs.trees.base_tree.0.get_term_roles() == rhs.trees.base_tree.0.get_term_roles(),
+            "The base trees of the two datasets do not share the same order"
+        );
+        (&lhs.trees.base_tree.1, &rhs.trees.base_tree.1)
+    }
+
+    fn new_tree_from_fusion<'a, BlockIterator>(&self, iterator: BlockIterator) -> Self
+        where BlockIterator: Iterator<Item=&'a Block<u32>> {
+        let mut new_tree = self.new_like();
+        new_tree.trees.base_tree.1.extend(iterator);
+        new_tree
+    }
+}
+
+/// Returns a hash set with the quads of an identifier list
+fn quad_set(encoded_quads: &[u32]) -> HashSet<[u32; 4]> {
+    encoded_quads
+        .chunks_exact(4)
+        .map(|quad| [quad[0], quad[1], quad[2], quad[3]])
+        .collect()
+}
+
+/// Set in which an element has been found by `merge_sorted`
+enum Merged<T> {
+    OnlyLeft(T),
+    Both(T),
+    OnlyRight(T)
+}
+
+/// Walks simultaneously two sorted iterators without duplicates, and tells for
+/// each element if it is in the left, the right or both iterators
+fn merge_sorted<T, L, R>(lhs: L, rhs: R) -> impl Iterator<Item=Merged<T>>
+    where T: Ord, L: Iterator<Item=T>, R: Iterator<Item=T> {
+    let mut lhs = lhs.peekable();
+    let mut rhs = rhs.peekable();
+
+    std::iter::from_fn(move || {
+        let ordering = match (lhs.peek(), rhs.peek()) {
+            (Some(l), Some(r)) => l.cmp(r),
+            (Some(_), None) => Ordering::Less,
+            (None, Some(_)) => Ordering::Greater,
+            (None, None) => return None
+        };
+
+        match ordering {
+            Ordering::Less    => lhs.next().map(Merged::OnlyLeft),
+            Ordering::Greater => rhs.next().map(Merged::OnlyRight),
+            Ordering::Equal   => {
+                rhs.next();
+                lhs.next().map(Merged::Both)
+            }
+        }
+    })
+}
+
+impl ForestOfIdentifierQuads {
+    /// Returns the number of quads that are only in `lhs`, in both datasets
+    /// and only in `rhs`.
+    fn overlap_sizes(lhs: &Self, rhs: &Self) -> (usize, usize, usize) {
+        let (lhs_tree, rhs_tree) = Self::base_trees(lhs, rhs);
+        let mut sizes = (0, 0, 0);
+
+        for merged in merge_sorted(lhs_tree.iter(), rhs_tree.iter()) {
+            match merged {
+                Merged::OnlyLeft(_)  => sizes.0 += 1,
+                Merged::Both(_)      => sizes.1 += 1,
+                Merged::OnlyRight(_) => sizes.2 += 1
+            }
+        }
+
+        sizes
+    }
+}
+
+#[wasm_bindgen(js_class="ForestOfIdentifierQuads")]
+impl ForestOfIdentifierQuads {
+    #[wasm_bindgen]
+    pub fn insersect(&self, other: &ForestOfIdentifierQuads) -> ForestOfIdentifierQuads {
+        let (self_tree, other_tree) = Self::base_trees(self, other);
+        self.new_tree_from_fusion(self_tree.intersection(other_tree))
+    }
+}
+
+#[wasm_bindgen(js_class="ForestOfIdentifierQuads")]
+impl ForestOfIdentifierQuads {
+    /// Returns, flattened, the quads that are in both datasets, without
+    /// building a new dataset like `insersect` does.
+    ///
+    /// The quads of both datasets are walked through simultaneously in the
+    /// order of the base tree (OGPS), which is the order of the returned
+    /// quads, like `getAllInBaseOrder`.
+    #[wasm_bindgen(js_name = intersectionIdentifierList)]
+    pub fn intersection_identifier_list(&self, other: &ForestOfIdentifierQuads) -> Box<[u32]> {
+        // Checks that the two datasets share the same base order
+        Self::base_trees(self, other);
+
+        let order = self.base_order();
+        let key = |quad: [u32; 4]| [quad[order[0]], quad[order[1]], quad[order[2]], quad[order[3]]];
+
+        let mut quads = vec!();
+
+        for merged in merge_sorted(self.base_order_quads().map(key), other.base_order_quads().map(key)) {
+            if let Merged::Both(quad_key) = merged {
+                let mut quad = [0u32; 4];
+                for (value, position) in quad_key.iter().zip(order.iter()) {
+                    quad[*position] = *value;
+                }
+                quads.extend_from_slice(&quad);
+            }
+        }
+
+        quads.into_boxed_slice()
+    }
+}
+
+#[wasm_bindgen(js_class="ForestOfIdentifierQuads")]
+impl ForestOfIdentifierQuads {
+    #[wasm_bindgen(js_name = intersectIdentifierList)]
+    pub fn intersect_slice(&self, other: &[u32]) -> Self {
+        let mut new_tree = self.new_like();
+
+        // Walk through the smallest side and search in the other one
+        if other.len() / 4 > self.size() {
+            let other_quads = quad_set(other);
+
+            for quad in self.matching_quads([None, None, None, None]) {
+                if other_quads.contains(&quad) {
+                    new_tree.trees.insert(quad);
+                }
+            }
+
+            return new_tree;
+        }
+
+        for i in 0..other.len() / 4 {
+            if self.has(other[i * 4], other[i * 4 + 1], other[i * 4 + 2], other[i * 4 + 3]) {
+                new_tree.add(other[i * 4], other[i * 4 + 1], other[i * 4 + 2], other[i * 4 + 3]);
+            }
+        }
+
+        new_tree
+    }
+
+    #[wasm_bindgen(js_name = unionIdentifierList)]
+    pub fn union_slice(&self, other: &[u32]) -> Self {
+        let mut new_tree = self.new_like();
+
+        for quad in self.matching_quads([None, None, None, None]) {
+            new_tree.add(quad[0], quad[1], quad[2], quad[3]);
+        }
+
+        for i in 0..other.len() / 4 {
+            new_tree.add(other[i * 4], other[i * 4 + 1], other[i * 4 + 2], other[i * 4 + 3]);
+        }
+
+        new_tree
+    }
+
+    #[wasm_bindgen(js_name = differenceIdentifierList)]
+    pub fn difference_slice(&self, other: &[u32]) -> Self {
+        let mut new_tree = self.new_like();
+
+        for quad in self.matching_quads([None, None, None, None]) {
+            new_tree.add(quad[0], quad[1], quad[2], quad[3]);
+        }
+
+        for i in 0..other.len() / 4 {
+            new_tree.trees.delete([other[i * 4], other[i * 4 + 1], other[i * 4 + 2], other[i * 4 + 3]]);
+        }
+
+        new_tree
+    }
+
+    /// Returns, flattened, the quads of this dataset that are not in the
+    /// identifier list `other`. This is `differenceIdentifierList` without
+    /// building a new dataset.
+    ///
+    /// Panics if the length of `other` is not a multiple of 4.
+    #[wasm_bindgen(js_name = missingFromSlice)]
+    pub fn missing_from_slice(&self, other: &[u32]) -> Box<[u32]> {
+        assert!(other.len() % 4 == 0);
+
+        let other_quads = quad_set(other);
+
+        self.matching_quads([None, None, None, None])
+            .filter(|quad| !other_quads.contains(quad))
+            .flatten()
+            .collect()
+    }
+}
+
+#[wasm_bindgen(js_class="ForestOfIdentifierQuads")]
+impl ForestOfIdentifierQuads {
+    #[wasm_bindgen]
+    pub fn union(&self, other: &ForestOfIdentifierQuads) -> Self {
+        let (self_tree, other_tree) = Self::base_trees(self, other);
+        self.new_tree_from_fusion(self_tree.union(other_tree))
+    }
+}
+
+#[wasm_bindgen(js_class="ForestOfIdentifierQuads")]
+impl ForestOfIdentifierQuads {
+    #[wasm_bindgen]
+    pub fn difference(&self, other: &ForestOfIdentifierQuads) -> Self {
+        let (self_tree, other_tree) = Self::base_trees(self, other);
+        self.new_tree_from_fusion(self_tree.difference(other_tree))
+    }
+}
+
+#[wasm_bindgen(js_class="ForestOfIdentifierQuads")]
+impl ForestOfIdentifierQuads {
+    /// Builds a new dataset with every quad whose subject is in `0..=s_max`,
+    /// predicate in `0..=p_max`, object in `0..=o_max` and graph in
+    /// `0..=g_max` that is not in this dataset.
+    ///
+    /// Every quad of the universe is explored, so this is only practical for
+    /// small domains. An error is thrown if the universe has more quads than
+    /// the limit set with `setMaxComplementUniverse`, 2^20 by default.
+    #[wasm_bindgen(js_name = complementWithin)]
+    pub fn complement_within(&self, s_max: u32, p_max: u32, o_max: u32, g_max: u32) -> Result<ForestOfIdentifierQuads, JsValue> {
+        let universe_size = [s_max, p_max, o_max, g_max]
+            .iter()
+            .try_fold(1u64, |size, max| size.checked_mul(*max as u64 + 1));
+
+        if !matches!(universe_size, Some(size) if size <= self.max_complement_universe as u64) {
+            return Err(JsValue::from_str("complementWithin: the universe has too many quads"));
+        }
+
+        let mut complement = self.new_like();
+
+        for s in 0..=s_max {
+            for p in 0..=p_max {
+                for o in 0..=o_max {
+                    for g in 0..=g_max {
+                        if !self.has(s, p, o, g) {
+                            complement.trees.insert([s, p, o, g]);
+                        }
+                    }
+                }
+            }
+        }
+
+        Ok(complement)
+    }
+
+    /// Sets the maximum number of quads of the universe that
+    /// `complementWithin` accepts to explore
+    #[wasm_bindgen(js_name = setMaxComplementUniverse)]
+    pub fn set_max_complement_universe(&mut self, max: usize) {
+        self.max_complement_universe = max;
+    }
+}
+
+#[wasm_bindgen(js_class="ForestOfIdentifierQuads")]
+impl ForestOfIdentifierQuads {
+    /// Adds the quads of `other` into this dataset and returns the number of
+    /// quads that were not already in it.
+    ///
+    /// Unlike `union`, no new dataset is built: the quads are inserted in the
+    /// trees of this dataset, so its built optional trees are kept and stay up
+    /// to date.
+    #[wasm_bindgen(js_name = unionInto)]
+    pub fn union_into(&mut self, other: &ForestOfIdentifierQuads) -> usize {
+        other.matching_quads([None, None, None, None])
+            .filter(|quad| self.insert_quad(*quad))
+            .count()
+    }
+}
+
+#[wasm_bindgen(js_class="ForestOfIdentifierQuads")]
+impl ForestOfIdentifierQuads {
+    /// Adds the quads of `other` that are in the given graph into this dataset
+    /// and returns the number of quads that were not already in it.
+    ///
+    /// This is `unionInto` restricted to one graph: no intermediate dataset is
+    /// built for the graph.
+    #[wasm_bindgen(js_name = importGraphFrom)]
+    pub fn import_graph_from(&mut self, other: &ForestOfIdentifierQuads, graph: u32) -> usize {
+        other.matching_quads([None, None, None, Some(graph)])
+            .filter(|quad| self.insert_quad(*quad))
+            .count()
+    }
+}
+
+#[wasm_bindgen(js_class="ForestOfIdentifierQuads")]
+impl ForestOfIdentifierQuads {
+    /// Removes from this dataset the quads that are not in `other`.
+    ///
+    /// Like `unionInto`, this dataset is modified in place and its built
+    /// optional trees are kept up to date.
+    #[wasm_bindgen(js_name = retainAll)]
+    pub fn retain_all(&mut self, other: &ForestOfIdentifierQuads) {
+        let quads_to_remove = self.matching_quads([None, None, None, None])
+            .filter(|quad| !other.has(quad[0], quad[1], quad[2], quad[3]))
+            .collect::<Vec<[u32; 4]>>();
+
+        for quad in quads_to_remove {
+            self.delete_quad(quad);
+        }
+    }
+}
+
+#[wasm_bindgen(js_class="ForestOfIdentifierQuads")]
+impl ForestOfIdentifierQuads {
+    #[wasm_bindgen]
+    pub fn contains(&self, other: &ForestOfIdentifierQuads) -> bool {
+        let (self_tree, other_tree) = Self::base_trees(self, other);
+        self_tree.is_superset(other_tree)
+    }
+}
+
+#[wasm_bindgen(js_class="ForestOfIdentifierQuads")]
+impl ForestOfIdentifierQuads {
+    /// Returns true if no quad is in both datasets. The search stops at the
+    /// first common quad.
+    #[wasm_bindgen(js_name = isDisjointFrom)]
+    pub fn is_disjoint_from(&self, other: &ForestOfIdentifierQuads) -> bool {
+        let (self_tree, other_tree) = Self::base_trees(self, other);
+        self_tree.is_disjoint(other_tree)
+    }
+}
+
+#[wasm_bindgen(js_class="ForestOfIdentifierQuads")]
+impl ForestOfIdentifierQuads {
+    #[wasm_bindgen(js_name = containsIdentifierList)]
+    pub fn contains_slice(&self, other: &[u32]) -> bool {
+        assert!(other.len() % 4 == 0);
+
+        // Walk through the smallest side and search in the other one
+        if other.len() / 4 > self.size() {
+            let other_quads = quad_set(other);
+            if other_quads.len() > self.size() {
+                return false;
+            }
+
+            let number_of_common_quads = self.matching_quads([None, None, None, None])
+                .filter(|quad| other_quads.contains(quad))
+                .count();
+            return number_of_common_quads == other_quads.len();
+        }
+
+        let mut i = 0;
+        while i != other.len() / 4 { 
+            if !self.has(other[i * 4], other[i * 4 + 1], other[i * 4 + 2], other[i * 4 + 3]) {
+                return false;
+            }
+            i = i + 1;
+        }
+
+        true
+    }
+}
+
+#[wasm_bindgen(js_class="ForestOfIdentifierQuads")]
+impl ForestOfIdentifierQuads {
+    /// Returns true if every quad of the identifier list `other` is in the
+    /// dataset, like `containsIdentifierList`.
+    ///
+    /// The quads of `other` must be sorted in the order of the base tree
+    /// (OGPS), like the list returned by `getAllInBaseOrder`, which lets both
+    /// lists be walked through only once. The same sorted list can be checked
+    /// against several datasets.
+    #[wasm_bindgen(js_name = containsSortedSlice)]
+    pub fn contains_sorted_slice(&self, other: &[u32]) -> bool {
+        assert!(other.len() % 4 == 0);
+
+        let order = self.base_order();
+        let key = |quad: &[u32]| [quad[order[0]], quad[order[1]], quad[order[2]], quad[order[3]]];
+
+        debug_assert!(other.chunks_exact(4).zip(other.chunks_exact(4).skip(1)).all(|(l, r)| key(l) <= key(r)));
+
+        let mut quads = self.base_order_quads().map(|quad| key(&quad)).peekable();
+
+        for quad in other.chunks_exact(4) {
+            let searched_key = key(quad);
+
+            while quads.next_if(|key| *key < searched_key).is_some() {}
+
+            if quads.peek() != Some(&searched_key) {
+                return false;
+            }
+        }
+
+        true
+    }
+}
+
+#[wasm_bindgen(js_class="ForestOfIdentifierQuads")]
+impl ForestOfIdentifierQuads {
+    #[wasm_bindgen(js_name = equalsIdentifierList)]
+    pub fn equals_slice(&self, other: &[u32]) -> bool {
+        if self.trees.base_tree.1.len() != other.len() / 4 {
+            return false;
+        }
+
+        self.contains_slice(other)
+    }
+}
+
+#[wasm_bindgen(js_class="ForestOfIdentifierQuads")]
+impl ForestOfIdentifierQuads {
+    #[wasm_bindgen]
+    pub fn has_same_elements(&self, other: &ForestOfIdentifierQuads) -> bool {
+        if self.trees.base_tree.1.len() != other.trees.base_tree.1.len() {
+            return false;
+        }
+
+        self.contains(other)
+    }
+}
+
+#[wasm_bindgen(js_class="ForestOfIdentifierQuads")]
+impl ForestOfIdentifierQuads {
+    /// Returns true if both datasets have the same triples, whatever the
+    /// graphs they are in
+    #[wasm_bindgen(js_name = equalsIgnoringGraph)]
+    pub fn equals_ignoring_graph(&self, other: &ForestOfIdentifierQuads) -> bool {
+        self.distinct_triples() == other.distinct_triples()
+    }
+}
+
+#[wasm_bindgen(js_class="ForestOfIdentifierQuads")]
+impl ForestOfIdentifierQuads {
+    /// Returns the Jaccard similarity of the two datasets, i.e. the size of
+    /// their intersection divided by the size of their union.
+    ///
+    /// The similarity of two empty datasets is 1.
+    #[wasm_bindgen]
+    pub fn jaccard(&self, other: &ForestOfIdentifierQuads) -> f64 {
+        let (only_self, common, only_other) = Self::overlap_sizes(self, other);
+        let union_size = only_self + common + only_other;
+
+        if union_size == 0 {
+            1.0
+        } else {
+            common as f64 / union_size as f64
+        }
+    }
+}
+
+#[wasm_bindgen(js_class="ForestOfIdentifierQuads")]
+impl ForestOfIdentifierQuads {
+    /// Returns the number of quads that are in both datasets
+    #[wasm_bindgen(js_name = intersectionSize)]
+    pub fn intersection_size(&self, other: &ForestOfIdentifierQuads) -> usize {
+        Self::overlap_sizes(self, other).1
+    }
+
+    /// Returns the number of quads that are in at least one of the datasets
+    #[wasm_bindgen(js_name = unionSize)]
+    pub fn union_size(&self, other: &ForestOfIdentifierQuads) -> usize {
+        let (only_self, common, only_other) = Self::overlap_sizes(self, other);
+        only_self + common + only_other
+    }
+
+    /// Returns the number of quads that are in this dataset but not in other
+    #[wasm_bindgen(js_name = differenceSize)]
+    pub fn difference_size(&self, other: &ForestOfIdentifierQuads) -> usize {
+        Self::overlap_sizes(self, other).0
+    }
+}
+
+#[wasm_bindgen(js_class="ForestOfIdentifierQuads")]
+impl ForestOfIdentifierQuads {
+    /// Returns the changes to apply to this dataset to get `other`, graph by
+    /// graph: an array with one `{ graph, added, removed }` object per graph
+    /// used by a quad of either dataset, sorted by graph identifier. `added`
+    /// is the flattened list of the quads of the graph that are only in
+    /// `other`, and `removed` of the ones that are only in this dataset.
+    #[wasm_bindgen(js_name = diffByGraph)]
+    pub fn diff_by_graph(&self, other: &ForestOfIdentifierQuads) -> Result<js_sys::Array, JsValue> {
+        // Sorting the quads by graph first makes the quads of a graph contiguous
+        let gspo = |quads: Vec<[u32; 4]>| quads.into_iter().map(|quad| [quad[3], quad[0], quad[1], quad[2]]);
+        let order = [3, 0, 1, 2];
+
+        let merged = merge_sorted(
+            gspo(self.sorted_matching_quads([None, None, None, None], order)),
+            gspo(other.sorted_matching_quads([None, None, None, None], order))
+        );
+
+        let mut graphs: Vec<(u32, Vec<u32>, Vec<u32>)> = vec!();
+
+        for merged_quad in merged {
+            let key = match &merged_quad {
+                Merged::OnlyLeft(key) | Merged::Both(key) | Merged::OnlyRight(key) => *key
+            };
+
+            if graphs.last().map(|graph| graph.0) != Some(key[0]) {
+                graphs.push((key[0], vec!(), vec!()));
+            }
+
+            let graph = graphs.last_mut().unwrap();
+            let quad = [key[1], key[2], key[3], key[0]];
+            match merged_quad {
+                Merged::OnlyLeft(_)  => graph.2.extend_from_slice(&quad),
+                Merged::Both(_)      => {},
+                Merged::OnlyRight(_) => graph.1.extend_from_slice(&quad)
+            }
+        }
+
+        let diff = js_sys::Array::new();
+
+        for (graph, added, removed) in graphs {
+            let graph_diff = js_sys::Object::new();
+            js_sys::Reflect::set(&graph_diff, &JsValue::from_str("graph"), &JsValue::from(graph))?;
+            js_sys::Reflect::set(&graph_diff, &JsValue::from_str("added"), &js_sys::Uint32Array::from(&added[..]))?;
+            js_sys::Reflect::set(&graph_diff, &JsValue::from_str("removed"), &js_sys::Uint32Array::from(&removed[..]))?;
+            diff.push(&graph_diff);
+        }
+
+        Ok(diff)
+    }
+}
+
+#[wasm_bindgen(js_class="ForestOfIdentifierQuads")]
+impl ForestOfIdentifierQuads {
+    /// Counts the number of quads that matches the given pattern
+    #[wasm_bindgen(js_name = matchCount)]
+    pub fn match_count(&self, s: Option<u32>, p: Option<u32>, o: Option<u32>, g: Option<u32>) -> usize {
+        let spog = [s, p, o, g];
+        return self.matching_quads(spog).count();
+    }
+}
+
+#[wasm_bindgen(js_class="ForestOfIdentifierQuads")]
+impl ForestOfIdentifierQuads {
+    /// Counts the number of quads whose term at `position` (0 for the subject
+    /// to 3 for the graph) is `value`. An error is thrown if the position is
+    /// not valid.
+    ///
+    /// This is `matchCount` with only one fixed term: the quads are read from a
+    /// tree that starts with this position, so only the matching quads are
+    /// walked.
+    #[wasm_bindgen(js_name = countWithValueAt)]
+    pub fn count_with_value_at(&self, position: u8, value: u32) -> Result<usize, JsValue> {
+        let mut spog = [None, None, None, None];
+
+        match spog.get_mut(position as usize) {
+            Some(term) => *term = Some(value),
+            None => return Err(JsValue::from_str("countWithValueAt: the position must be between 0 and 3"))
+        }
+
+        Ok(self.matching_quads(spog).count())
+    }
+}
+
+impl ForestOfIdentifierQuads {
+    /// Returns the number of distinct terms at each position.
+    ///
+    /// They are read from the statistics if they are enabled. Else, they are
+    /// computed with a full scan on the first call, and then kept until the
+    /// dataset is modified.
+    fn term_cardinalities(&self) -> [usize; 4] {
+        if let Some(statistics) = self.statistics.as_ref() {
+            return statistics.cardinalities();
+        }
+
+        if let Some(cardinalities) = self.term_cardinalities.get() {
+            return cardinalities;
+        }
+
+        let mut terms: [HashSet<u32>; 4] = Default::default();
+        for quad in self.matching_quads([None, None, None, None]) {
+            for (position_terms, term) in terms.iter_mut().zip(quad.iter()) {
+                position_terms.insert(*term);
+            }
+        }
+
+        let cardinalities = [terms[0].len(), terms[1].len(), terms[2].len(), terms[3].len()];
+        self.term_cardinalities.set(Some(cardinalities));
+        cardinalities
+    }
+}
+
+#[wasm_bindgen(js_class="ForestOfIdentifierQuads")]
+impl ForestOfIdentifierQuads {
+    /// Returns an estimate of the number of quads that match the given
+    /// pattern, without searching them.
+    ///
+    /// The terms are assumed to be independent and uniformly distributed, so
+    /// each fixed term divides the size of the dataset by its number of
+    /// distinct values. The distinct values are counted once, and again only
+    /// after the dataset is modified. The real number of matching quads may
+    /// be very different: use `matchCount` to get it.
+    #[wasm_bindgen(js_name = estimateMatchCount)]
+    pub fn estimate_match_count(&self, s: Option<u32>, p: Option<u32>, o: Option<u32>, g: Option<u32>) -> usize {
+        if self.size() == 0 {
+            return 0;
+        }
+
+        let cardinalities = self.term_cardinalities();
+
+        [s, p, o, g].iter()
+            .zip(cardinalities.iter())
+            .filter(|(term, _)| term.is_some())
+            .fold(self.size() as f64, |estimate, (_, cardinality)| estimate / *cardinality as f64)
+            .round() as usize
+    }
+}
+
+#[wasm_bindgen(js_class="ForestOfIdentifierQuads")]
+impl ForestOfIdentifierQuads {
+    /// Counts the number of quads that match each pattern.
+    ///
+    /// `patterns` is a list of groups of 4 values, written like in
+    /// `matchBindings`: -1 for the positions that can be any term. An error is
+    /// thrown if a pattern contains another negative value.
+    #[wasm_bindgen(js_name = countMatchingEach)]
+    pub fn count_matching_each(&self, patterns: &[i32]) -> Result<Box<[u32]>, JsValue> {
+        assert!(patterns.len() % 4 == 0);
+
+        patterns
+            .chunks(4)
+            .map(|pattern| to_spog_pattern([pattern[0], pattern[1], pattern[2], pattern[3]]))
+            .map(|spog| spog.map(|spog| self.matching_quads(spog).count() as u32))
+            .collect()
+    }
+}
+
+#[wasm_bindgen(js_class="ForestOfIdentifierQuads")]
+impl ForestOfIdentifierQuads {
+    /// Returns true if at least one quad matches the given pattern.
+    ///
+    /// Unlike `matchCount`, stops as soon as a matching quad is found.
+    #[wasm_bindgen(js_name = ask)]
+    pub fn ask(&self, s: Option<u32>, p: Option<u32>, o: Option<u32>, g: Option<u32>) -> bool {
+        self.matching_quads([s, p, o, g]).next().is_some()
+    }
+}
+
+#[wasm_bindgen(js_class="ForestOfIdentifierQuads")]
+impl ForestOfIdentifierQuads {
+    /// Returns the first quad that matches the given pattern, or undefined if
+    /// there is none.
+    #[wasm_bindgen(js_name = firstMatch)]
+    pub fn first_match(&self, s: Option<u32>, p: Option<u32>, o: Option<u32>, g: Option<u32>) -> Option<Box<[u32]>> {
+        self.matching_quads([s, p, o, g])
+            .next()
+            .map(|quad| Box::new(quad) as Box<[u32]>)
+    }
+}
+
+#[wasm_bindgen(js_class="ForestOfIdentifierQuads")]
+impl ForestOfIdentifierQuads {
+    /// Returns the last quad that matches the given pattern in the order of
+    /// the tree used to answer the query, or undefined if there is none.
+    ///
+    /// If the dataset is frozen, its list is read backward from the end of
+    /// the range of the pattern. Else, as the iterator of identifier_forest
+    /// only moves forward, every matching quad is visited.
+    #[wasm_bindgen(js_name = lastMatch)]
+    pub fn last_match(&self, s: Option<u32>, p: Option<u32>, o: Option<u32>, g: Option<u32>) -> Option<Box<[u32]>> {
+        let spog = [s, p, o, g];
+
+        let last_quad = match self.frozen.as_ref() {
+            Some(frozen) => sorted_list_range(frozen, &spog).iter().rev().copied().find(pattern_matcher(spog)),
+            None => self.matching_quads(spog).last()
+        };
+
+        last_quad.map(|quad| Box::new(quad) as Box<[u32]>)
+    }
+}
+
+#[wasm_bindgen(js_class="ForestOfIdentifierQuads")]
+impl ForestOfIdentifierQuads {
+    /// Ensures the best tree to search quads matching the given pattern is built
+    #[wasm_bindgen(js_name = ensureHasIndexfor)]
+    pub fn ensure_has_index_for(&mut self, s: bool, p: bool, o: bool, g: bool) {
+        self.trees.ensure_has_index_for(s, p, o, g);
+
+        // Protect the tree we may have built from the eviction
+        let as_pattern = |is_fixed: bool| if is_fixed { Some(0) } else { None };
+        self.record_use(&[as_pattern(s), as_pattern(p), as_pattern(o), as_pattern(g)]);
+        self.evict_least_recently_used_trees();
+    }
+}
+
+#[wasm_bindgen(js_class="ForestOfIdentifierQuads")]
+impl ForestOfIdentifierQuads {
+    /// Counts the number of living trees
+    #[wasm_bindgen(js_name = getNumberOfLivingTrees)]
+    pub fn get_number_of_living_trees(&self) -> usize {
+        self.trees.get_number_of_living_trees()
+    }
+}
+
+// ==== Graph management
+
+#[wasm_bindgen(js_class="ForestOfIdentifierQuads")]
+impl ForestOfIdentifierQuads {
+    /// Moves every quad of the graph `from` into the graph `to`.
+    ///
+    /// If a moved quad already exists in the graph `to`, the two quads are
+    /// merged into one.
+    #[wasm_bindgen(js_name = renameGraph)]
+    pub fn rename_graph(&mut self, from: u32, to: u32) {
+        if from == to {
+            return;
+        }
+
+        // Collect first as we can not modify the trees while iterating on them
+        let quads = self.matching_quads([None, None, None, Some(from)]).collect::<Vec<[u32; 4]>>();
+
+        for quad in quads {
+            self.remove(quad[0], quad[1], quad[2], quad[3]);
+            self.add(quad[0], quad[1], quad[2], to);
+        }
+    }
+}
+
+#[wasm_bindgen(js_class="ForestOfIdentifierQuads")]
+impl ForestOfIdentifierQuads {
+    /// Removes every quad of the graph `g` and returns the number of removed
+    /// quads.
+    ///
+    /// Equivalent to `deleteMatches(null, null, null, g)`, but rebuilds the
+    /// trees sooner as wiping a graph usually removes a big part of the
+    /// dataset.
+    #[wasm_bindgen(js_name = clearGraph)]
+    pub fn clear_graph(&mut self, g: u32) -> usize {
+        let ratio_threshhold = 4 + self.number_of_optional_built_trees();
+        self.delete_matches_with_threshold([None, None, None, Some(g)], ratio_threshhold)
+    }
+}
+
+#[wasm_bindgen(js_class="ForestOfIdentifierQuads")]
+impl ForestOfIdentifierQuads {
+    /// Copies every quad of the graph `from` into the graph `to`. The quads of
+    /// the graph `from` are kept.
+    #[wasm_bindgen(js_name = copyGraph)]
+    pub fn copy_graph(&mut self, from: u32, to: u32) {
+        if from == to {
+            return;
+        }
+
+        let quads = self.matching_quads([None, None, None, Some(from)]).collect::<Vec<[u32; 4]>>();
+
+        for quad in quads {
+            self.add(quad[0], quad[1], quad[2], to);
+        }
+    }
+}
+
+#[wasm_bindgen(js_class="ForestOfIdentifierQuads")]
+impl ForestOfIdentifierQuads {
+    /// Replaces the content of the graph `g` with the triples of
+    /// `encoded_triples`, a list of `[s, p, o]` identifiers.
+    ///
+    /// The list is checked before the graph is cleared: if its length is not
+    /// a multiple of 3, this panics and the graph is left untouched.
+    #[wasm_bindgen(js_name = replaceGraph)]
+    pub fn replace_graph(&mut self, g: u32, encoded_triples: &[u32]) {
+        assert!(encoded_triples.len() % 3 == 0);
+
+        self.clear_graph(g);
+
+        for triple in encoded_triples.chunks_exact(3) {
+            self.add(triple[0], triple[1], triple[2], g);
+        }
+    }
+}
+
+#[wasm_bindgen(js_class="ForestOfIdentifierQuads")]
+impl ForestOfIdentifierQuads {
+    /// Builds a new dataset in which the graph of every quad is replaced with
+    /// `default_graph_id`, which becomes the default graph of the new dataset.
+    ///
+    /// Triples that appear in several graphs are merged, so the new dataset
+    /// may be smaller than this one.
+    #[wasm_bindgen(js_name = projectToDefaultGraph)]
+    pub fn project_to_default_graph(&self, default_graph_id: u32) -> Self {
+        let mut new_tree = Self::with_default_graph(default_graph_id);
+
+        for quad in self.matching_quads([None, None, None, None]) {
+            new_tree.add(quad[0], quad[1], quad[2], default_graph_id);
+        }
+
+        new_tree
+    }
+}
+
+#[wasm_bindgen(js_class="ForestOfIdentifierQuads")]
+impl ForestOfIdentifierQuads {
+    /// Returns, flattened, the distinct `[s, p, o]` triples of the dataset,
+    /// whatever their graph is. The triples are sorted in SPO order.
+    ///
+    /// The quads are read sorted in SPOG order, so the quads of a triple are
+    /// consecutive and are merged without building a set. They are only
+    /// sorted if the dataset is not frozen.
+    #[wasm_bindgen(js_name = distinctTriples)]
+    pub fn distinct_triples(&self) -> Box<[u32]> {
+        let mut triples = vec!();
+
+        count_by_key(
+            self.sorted_matching_quads([None, None, None, None], SPOG).into_iter(),
+            true,
+            |quad| [quad[0], quad[1], quad[2]],
+            |triple, _| triples.extend_from_slice(&triple)
+        );
+
+        triples.into_boxed_slice()
+    }
+}
+
+#[wasm_bindgen(js_class="ForestOfIdentifierQuads")]
+impl ForestOfIdentifierQuads {
+    /// Returns, flattened, the distinct `[s, p]` pairs of the dataset, sorted
+    /// by subject then by predicate, like `distinctTriples`
+    #[wasm_bindgen(js_name = distinctSubjectPredicatePairs)]
+    pub fn distinct_sp_pairs(&self) -> Box<[u32]> {
+        let mut pairs = vec!();
+
+        count_by_key(
+            self.sorted_matching_quads([None, None, None, None], SPOG).into_iter(),
+            true,
+            |quad| [quad[0], quad[1]],
+            |pair, _| pairs.extend_from_slice(&pair)
+        );
+
+        pairs.into_boxed_slice()
+    }
+}
+
+#[wasm_bindgen(js_class="ForestOfIdentifierQuads")]
+impl ForestOfIdentifierQuads {
+    /// Returns the triples that are in at least two graphs, as a flattened
+    /// list of `[s, p, o, graphCount]` records sorted in SPO order
+    #[wasm_bindgen(js_name = crossGraphDuplicates)]
+    pub fn cross_graph_duplicates(&self) -> Box<[u32]> {
+        let mut records = vec!();
+
+        // The quads are distinct, so the quads of a triple are in distinct graphs
+        count_by_key(
+            self.sorted_matching_quads([None, None, None, None], SPOG).into_iter(),
+            true,
+            |quad| [quad[0], quad[1], quad[2]],
+            |triple, number_of_graphs| if number_of_graphs >= 2 {
+                records.extend_from_slice(&triple);
+                records.push(number_of_graphs);
+            }
+        );
+
+        records.into_boxed_slice()
+    }
+}
+
+#[wasm_bindgen(js_class="ForestOfIdentifierQuads")]
+impl ForestOfIdentifierQuads {
+    /// Returns the number of distinct graphs used by at least one quad. The
+    /// default graph is only counted if `include_default_graph` is true.
+    #[wasm_bindgen(js_name = countGraphs)]
+    pub fn count_graphs(&self, include_default_graph: bool) -> usize {
+        self.matching_quads([None, None, None, None])
+            .map(|quad| quad[3])
+            .filter(|graph| include_default_graph || *graph != self.default_graph_id)
+            .collect::<BTreeSet<u32>>()
+            .len()
+    }
+
+    /// Returns true if at least one quad is in the graph `g`
+    #[wasm_bindgen(js_name = hasGraph)]
+    pub fn has_graph(&self, g: u32) -> bool {
+        self.matching_quads([None, None, None, Some(g)]).next().is_some()
+    }
+}
+
+#[wasm_bindgen(js_class="ForestOfIdentifierQuads")]
+impl ForestOfIdentifierQuads {
+    /// Returns, flattened, the `[s, p, o]` triples of the quads that are in
+    /// the default graph, see `defaultGraphId`
+    #[wasm_bindgen(js_name = getDefaultGraph)]
+    pub fn get_default_graph(&self) -> Box<[u32]> {
+        self.matching_quads([None, None, None, Some(self.default_graph_id)])
+            .flat_map(|quad| quad[0..3].to_vec())
+            .collect()
+    }
+}
+
+#[wasm_bindgen(js_class="ForestOfIdentifierQuads")]
+impl ForestOfIdentifierQuads {
+    /// Returns an array with one `{ graph, dataset }` object per graph, sorted
+    /// by graph identifier, where `dataset` contains the quads of `graph`.
+    ///
+    /// If `keep_graph` is false, the quads of every dataset are moved to its
+    /// default graph.
+    ///
+    /// The quads are read once, sorted by graph, and each run of quads with the
+    /// same graph becomes a dataset. They are only sorted if the full scan does
+    /// not already return them in this order.
+    #[wasm_bindgen(js_name = splitByGraph)]
+    pub fn split_by_graph(&self, keep_graph: bool) -> Result<js_sys::Array, JsValue> {
+        let parts = js_sys::Array::new();
+
+        let push_part = |graph: u32, dataset: ForestOfIdentifierQuads| -> Result<(), JsValue> {
+            let part = js_sys::Object::new();
+            js_sys::Reflect::set(&part, &JsValue::from_str("graph"), &JsValue::from(graph))?;
+            js_sys::Reflect::set(&part, &JsValue::from_str("dataset"), &JsValue::from(dataset))?;
+            parts.push(&part);
+            Ok(())
+        };
+
+        let mut current: Option<(u32, ForestOfIdentifierQuads)> = None;
+
+        for quad in self.sorted_matching_quads([None, None, None, None], [3, 0, 1, 2]) {
+            let graph = quad[3];
+
+            if !matches!(current, Some((current_graph, _)) if current_graph == graph) {
+                if let Some((previous_graph, dataset)) = current.replace((graph, self.new_like())) {
+                    push_part(previous_graph, dataset)?;
+                }
+            }
+
+            if let Some((_, dataset)) = current.as_mut() {
+                let part_graph = if keep_graph { graph } else { dataset.default_graph_id };
+                dataset.trees.insert([quad[0], quad[1], quad[2], part_graph]);
+            }
+        }
+
+        if let Some((last_graph, dataset)) = current {
+            push_part(last_graph, dataset)?;
+        }
+
+        Ok(parts)
+    }
+}
+
+#[wasm_bindgen(js_class="ForestOfIdentifierQuads")]
+impl ForestOfIdentifierQuads {
+    /// Returns an array with one dataset per predicate of `predicates`, with
+    /// the quads that have this predicate, followed by a dataset with the
+    /// quads whose predicate is not in `predicates`.
+    #[wasm_bindgen(js_name = partitionByPredicate)]
+    pub fn partition_by_predicate(&self, predicates: &[u32]) -> js_sys::Array {
+        let parts = js_sys::Array::new();
+
+        for &predicate in predicates {
+            parts.push(&JsValue::from(self.new_from(None, Some(predicate), None, None)));
+        }
+
+        let listed_predicates = predicates.iter().collect::<BTreeSet<&u32>>();
+        let mut rest = self.new_like();
+        for quad in self.matching_quads([None, None, None, None]) {
+            if !listed_predicates.contains(&quad[1]) {
+                rest.trees.insert(quad);
+            }
+        }
+        parts.push(&JsValue::from(rest));
+
+        parts
+    }
+}
+
+// ==== Sampling
+
+/// A small xorshift pseudo random number generator. It is not suitable for
+/// anything else than picking reproducible samples.
+struct XorShift64 {
+    state: u64
+}
+
+impl XorShift64 {
+    fn new(seed: u32) -> Self {
+        // The state of a xorshift generator must never be 0
+        Self { state: (seed as u64) << 32 | 0x9E37_79B9 }
+    }
+
+    /// Returns a pseudo random number in `0..bound`
+    fn next_below(&mut self, bound: usize) -> usize {
+        self.state ^= self.state << 13;
+        self.state ^= self.state >> 7;
+        self.state ^= self.state << 17;
+        (self.state % bound as u64) as usize
+    }
+}
+
+#[wasm_bindgen(js_class="ForestOfIdentifierQuads")]
+impl ForestOfIdentifierQuads {
+    /// Returns `n` quads picked pseudo randomly in the dataset, flattened.
+    ///
+    /// The same seed always gives the same sample for the same dataset. If
+    /// `n >= size()`, every quad is returned.
+    #[wasm_bindgen(js_name = sampleQuads)]
+    pub fn sample_quads(&self, n: usize, seed: u32) -> Box<[u32]> {
+        // Reservoir sampling
+        let mut generator = XorShift64::new(seed);
+        let mut reservoir = Vec::with_capacity(n.min(self.size()));
+
+        for (i, quad) in self.matching_quads([None, None, None, None]).enumerate() {
+            if i < n {
+                reservoir.push(quad);
+            } else {
+                let j = generator.next_below(i + 1);
+                if j < n {
+                    reservoir[j] = quad;
+                }
+            }
+        }
+
+        reservoir.iter().flatten().copied().collect()
+    }
+}
+
+// ==== Grouping
+
+#[wasm_bindgen(js_class="ForestOfIdentifierQuads")]
+impl ForestOfIdentifierQuads {
+    /// Returns the quads grouped by subject.
+    ///
+    /// The returned list is the concatenation of one record per subject. A
+    /// record is `[subject, n, p1, o1, g1, ..., pn, on, gn]`: the subject
+    /// identifier, the number `n` of quads that have this subject, and then
+    /// the predicate, object and graph of each of these quads. Records are
+    /// sorted by subject and the quads of a record in POG order.
+    ///
+    /// The subjects are counted first. The quads of each subject are then read
+    /// from a tree that starts with the subject, which is built if needed, so
+    /// only the quads of one subject are copied at the same time.
+    #[wasm_bindgen(js_name = groupBySubject)]
+    pub fn group_by_subject(&self) -> Box<[u32]> {
+        let mut subjects = vec!();
+        count_by_key(
+            self.matching_quads([None, None, None, None]),
+            self.scan_is_sorted_by(&[0]),
+            |quad| quad[0],
+            |subject, number_of_quads| subjects.push((subject, number_of_quads))
+        );
+
+        let mut records = Vec::with_capacity(subjects.len() * 2 + self.size() * 3);
+
+        for (subject, number_of_quads) in subjects {
+            records.push(subject);
+            records.push(number_of_quads);
+
+            // Only sorted if the dataset is not frozen
+            let group = self.sorted_matching_quads([Some(subject), None, None, None], SPOG);
+            for quad in group {
+                records.extend_from_slice(&quad[1..4]);
+            }
+        }
+
+        records.into_boxed_slice()
+    }
+}
+
+#[wasm_bindgen(js_class="ForestOfIdentifierQuads")]
+impl ForestOfIdentifierQuads {
+    /// Returns, for each value of the term at `group_position` in the quads
+    /// that match the pattern, the number of these quads, as a flattened list
+    /// of `[value, count]` pairs sorted by value.
+    ///
+    /// The term at `group_position` must be free in the pattern, or an error
+    /// is thrown. The matching quads are read from the tree that starts with
+    /// the fixed terms and are not copied: the values are counted in a map
+    /// with one entry per value, unless the pattern has no fixed term and the
+    /// scan is already sorted by the grouped term.
+    #[wasm_bindgen(js_name = countGroupedBy)]
+    pub fn count_grouped_by(&self, s: Option<u32>, p: Option<u32>, o: Option<u32>, g: Option<u32>, group_position: u8)
+        -> Result<Box<[u32]>, JsValue> {
+        let spog = [s, p, o, g];
+        let position = group_position as usize;
+
+        if position >= 4 || spog[position].is_some() {
+            return Err(JsValue::from_str("countGroupedBy: the grouped term must be free in the pattern"));
+        }
+
+        let is_full_scan = spog == [None, None, None, None];
+        let mut pairs = vec!();
+
+        count_by_key(
+            self.matching_quads(spog),
+            is_full_scan && self.scan_is_sorted_by(&[position]),
+            |quad| quad[position],
+            |value, count| {
+                pairs.push(value);
+                pairs.push(count);
+            }
+        );
+
+        Ok(pairs.into_boxed_slice())
+    }
+}
+
+#[wasm_bindgen(js_class="ForestOfIdentifierQuads")]
+impl ForestOfIdentifierQuads {
+    /// Returns, flattened, every quad in which `node` appears, whatever its
+    /// position is. Each quad is returned once.
+    ///
+    /// The quads are searched with one pattern per position, answered by the
+    /// SPOG, POGS, OGPS (base) and GPSO or GSPO trees respectively. The
+    /// optional trees are built if needed.
+    #[wasm_bindgen(js_name = describeNode)]
+    pub fn describe_node(&self, node: u32) -> Box<[u32]> {
+        let mut quads = vec!();
+
+        for position in 0..4 {
+            let mut spog = [None, None, None, None];
+            spog[position] = Some(node);
+
+            // A quad that contains node in a previous position has already
+            // been found by a previous pattern
+            quads.extend(
+                self.matching_quads(spog)
+                    .filter(|quad| !quad[0..position].contains(&node))
+                    .flatten()
+            );
+        }
+
+        quads.into_boxed_slice()
+    }
+}
+
+#[wasm_bindgen(js_class="ForestOfIdentifierQuads")]
+impl ForestOfIdentifierQuads {
+    /// Builds a new dataset with the concise bounded description of `start`.
+    ///
+    /// The description contains every quad that has `start` as subject and,
+    /// recursively, the quads that have as subject a blank node that is in
+    /// object position of a quad of the description. Identifiers greater than
+    /// or equal to `blank_node_min_id` are considered to be blank nodes.
+    #[wasm_bindgen(js_name = conciseBoundedDescription)]
+    pub fn cbd(&self, start: u32, blank_node_min_id: u32) -> Self {
+        let mut description = self.new_like();
+
+        let mut visited_nodes = BTreeSet::new();
+        visited_nodes.insert(start);
+        let mut nodes_to_explore = vec![start];
+
+        while let Some(node) = nodes_to_explore.pop() {
+            for quad in self.matching_quads([Some(node), None, None, None]) {
+                description.trees.insert(quad);
+
+                if quad[2] >= blank_node_min_id && visited_nodes.insert(quad[2]) {
+                    nodes_to_explore.push(quad[2]);
+                }
+            }
+        }
+
+        description
+    }
+}
+
+// ==== Graph analysis
+
+#[wasm_bindgen(js_class="ForestOfIdentifierQuads")]
+impl ForestOfIdentifierQuads {
+    /// Returns the number of quads that have `node` as subject, in the given
+    /// graph or in any graph
+    #[wasm_bindgen(js_name = outDegree)]
+    pub fn out_degree(&self, node: u32, graph: Option<u32>) -> usize {
+        self.match_count(Some(node), None, None, graph)
+    }
+
+    /// Returns the number of quads that have `node` as object, in the given
+    /// graph or in any graph
+    #[wasm_bindgen(js_name = inDegree)]
+    pub fn in_degree(&self, node: u32, graph: Option<u32>) -> usize {
+        self.match_count(None, None, Some(node), graph)
+    }
+}
+
+#[wasm_bindgen(js_class="ForestOfIdentifierQuads")]
+impl ForestOfIdentifierQuads {
+    /// Returns, flattened, the quads whose subject is also the object, in the
+    /// given graph or in any graph.
+    ///
+    /// No tree is sorted in a way that helps this search, so every quad of
+    /// the graph is read: it runs in O(n).
+    #[wasm_bindgen(js_name = reflexiveQuads)]
+    pub fn reflexive_quads(&self, graph: Option<u32>) -> Box<[u32]> {
+        self.matching_quads([None, None, None, graph])
+            .filter(|quad| quad[0] == quad[2])
+            .flatten()
+            .collect()
+    }
+}
+
+impl ForestOfIdentifierQuads {
+    /// Returns the `k` terms that appear in the most quads at the given
+    /// position, as a flattened list of `[term, count]` pairs sorted by
+    /// decreasing count. Terms that appear in the same number of quads are
+    /// sorted by identifier.
+    ///
+    /// The quads are read once without being copied, and only the `k` best
+    /// counts are kept. If a full scan returns the quads grouped by the term
+    /// at `position`, like the objects with the OGPS base tree, the terms are
+    /// counted one after the other, so at most `k + 1` counts are held. Else,
+    /// every term at this position is counted in a map first.
+    fn most_frequent_terms(&self, k: usize, position: usize) -> Box<[u32]> {
+        if k == 0 {
+            return Box::new([]);
+        }
+
+        let mut best = BinaryHeap::with_capacity(k + 1);
+
+        count_by_key(
+            self.matching_quads([None, None, None, None]),
+            self.scan_is_sorted_by(&[position]),
+            |quad| quad[position],
+            |term, count| {
+                // The heap pops its greatest element, so it is reversed to pop
+                // the least frequent term
+                best.push(Reverse((count, Reverse(term))));
+
+                if best.len() > k {
+                    best.pop();
+                }
+            }
+        );
+
+        let mut pairs = Vec::with_capacity(best.len() * 2);
+        for Reverse((count, Reverse(term))) in best.into_sorted_vec() {
+            pairs.push(term);
+            pairs.push(count);
+        }
+
+        pairs.into_boxed_slice()
+    }
+}
+
+#[wasm_bindgen(js_class="ForestOfIdentifierQuads")]
+impl ForestOfIdentifierQuads {
+    /// Returns the `k` predicates that appear in the most quads, as a
+    /// flattened list of `[predicate, count]` pairs sorted by decreasing count.
+    /// Predicates that appear in the same number of quads are sorted by
+    /// identifier.
+    ///
+    /// The quads are not copied, but neither the base tree nor the frozen list
+    /// is sorted by predicate, so every predicate is counted before the `k`
+    /// best are kept.
+    #[wasm_bindgen(js_name = topPredicates)]
+    pub fn top_predicates(&self, k: usize) -> Box<[u32]> {
+        self.most_frequent_terms(k, 1)
+    }
+}
+
+#[wasm_bindgen(js_class="ForestOfIdentifierQuads")]
+impl ForestOfIdentifierQuads {
+    /// Returns the `k` nodes with the greatest out degree if `direction` is 0,
+    /// or in degree if `direction` is 1, as a flattened list of
+    /// `[node, degree]` pairs sorted by decreasing degree. Nodes that have the
+    /// same degree are sorted by identifier.
+    ///
+    /// The degrees are computed over every graph, like `outDegree` and
+    /// `inDegree` without a graph. The in degrees are counted while the OGPS
+    /// base tree is read, so only `k + 1` of them are held. The out degrees
+    /// are only counted this way if the dataset is frozen, else every subject
+    /// is counted in a map first.
+    #[wasm_bindgen(js_name = topDegreeNodes)]
+    pub fn top_degree_nodes(&self, k: usize, direction: u8) -> Result<Box<[u32]>, JsValue> {
+        match direction {
+            0 => Ok(self.most_frequent_terms(k, 0)),
+            1 => Ok(self.most_frequent_terms(k, 2)),
+            _ => Err(JsValue::from_str("The direction must be 0 (out degree) or 1 (in degree)"))
+        }
+    }
+}
+
+#[wasm_bindgen(js_class="ForestOfIdentifierQuads")]
+impl ForestOfIdentifierQuads {
+    /// Returns `[minId, maxId]`, the lowest and the greatest identifiers that
+    /// appear in any position of a quad, or an empty list if the dataset is
+    /// empty
+    #[wasm_bindgen(js_name = idBounds)]
+    pub fn id_bounds(&self) -> Box<[u32]> {
+        let bounds = self.matching_quads([None, None, None, None])
+            .flatten()
+            .fold(None, |bounds, id| match bounds {
+                None => Some((id, id)),
+                Some((min, max)) => Some((id.min(min), id.max(max)))
+            });
+
+        match bounds {
+            None => Box::new([]),
+            Some((min, max)) => Box::new([min, max])
+        }
+    }
+}
+
+#[wasm_bindgen(js_class="ForestOfIdentifierQuads")]
+impl ForestOfIdentifierQuads {
+    /// Returns the distinct nodes, other than `start`, that are the object of
+    /// a quad whose subject is an object of a quad whose subject is `start`,
+    /// in the given graph or in any graph. The nodes are sorted.
+    ///
+    /// Edges are only followed from subject to object. One pattern is
+    /// searched for `start` and one for each of its distinct neighbors, so the
+    /// cost is about the sum of their out degrees.
+    #[wasm_bindgen(js_name = twoHopNeighbors)]
+    pub fn two_hop_neighbors(&self, start: u32, graph: Option<u32>) -> Box<[u32]> {
+        let neighbors = self.matching_quads([Some(start), None, None, graph])
+            .map(|quad| quad[2])
+            .collect::<BTreeSet<u32>>();
+
+        let mut two_hop_neighbors = BTreeSet::new();
+        for neighbor in neighbors {
+            two_hop_neighbors.extend(
+                self.matching_quads([Some(neighbor), None, None, graph]).map(|quad| quad[2])
+            );
+        }
+
+        two_hop_neighbors.remove(&start);
+        two_hop_neighbors.into_iter().collect()
+    }
+}
+
+#[wasm_bindgen(js_class="ForestOfIdentifierQuads")]
+impl ForestOfIdentifierQuads {
+    /// Returns true if `to` can be reached from `from` by following, from
+    /// subject to object, quads whose predicate is `predicate`, in the given
+    /// graph or in any graph. A node can always reach itself.
+    ///
+    /// The nodes are explored breadth first and the search stops as soon as
+    /// `to` is found. If `max_depth` is given, paths longer than `max_depth`
+    /// quads are not explored.
+    #[wasm_bindgen(js_name = pathExists)]
+    pub fn path_exists(&self, from: u32, to: u32, predicate: u32, graph: Option<u32>, max_depth: Option<u32>) -> bool {
+        if from == to {
+            return true;
+        }
+
+        let mut visited_nodes = BTreeSet::new();
+        visited_nodes.insert(from);
+        let mut nodes_to_explore = VecDeque::new();
+        nodes_to_explore.push_back((from, 0));
+
+        while let Some((node, depth)) = nodes_to_explore.pop_front() {
+            if max_depth == Some(depth) {
+                continue;
+            }
+
+            for quad in self.matching_quads([Some(node), Some(predicate), None, graph]) {
+                if quad[2] == to {
+                    return true;
+                }
+
+                if visited_nodes.insert(quad[2]) {
+                    nodes_to_explore.push_back((quad[2], depth + 1));
+                }
+            }
+        }
+
+        false
+    }
+}
+
+/// The edges of a predicate in compressed sparse row format, see
+/// `predicateAdjacencyCSR`
+#[wasm_bindgen]
+pub struct AdjacencyCSR {
+    /// The subjects sorted by identifier. The rank of a subject is its
+    /// position in this list
+    subjects: Box<[u32]>,
+    /// The objects of the subject of rank `i` are at the positions
+    /// `row_offsets[i]` to `row_offsets[i + 1]` (excluded) of `targets`
+    row_offsets: Box<[u32]>,
+    targets: Box<[u32]>
+}
+
+#[wasm_bindgen]
+impl AdjacencyCSR {
+    /// Returns the subjects sorted by identifier: the rank of a subject in
+    /// the rows is its position in this list
+    #[wasm_bindgen(getter)]
+    pub fn subjects(&self) -> Box<[u32]> {
+        self.subjects.clone()
+    }
+
+    /// Returns one more offset than there are subjects: the objects of the
+    /// subject of rank `i` are `targets[rowOffsets[i]..rowOffsets[i + 1]]`
+    #[wasm_bindgen(getter, js_name = rowOffsets)]
+    pub fn row_offsets(&self) -> Box<[u32]> {
+        self.row_offsets.clone()
+    }
+
+    /// Returns the objects of every subject, row after row, each row sorted
+    /// by identifier
+    #[wasm_bindgen(getter)]
+    pub fn targets(&self) -> Box<[u32]> {
+        self.targets.clone()
+    }
+}
+
+#[wasm_bindgen(js_class="ForestOfIdentifierQuads")]
+impl ForestOfIdentifierQuads {
+    /// Returns the edges from subject to object of the quads whose predicate
+    /// is `predicate`, in the given graph or in any graph, in compressed
+    /// sparse row format.
+    ///
+    /// The subjects are ranked by increasing identifier and the `subjects`
+    /// list of the result maps the ranks back to identifiers. An edge that is
+    /// in several graphs appears once.
+    #[wasm_bindgen(js_name = predicateAdjacencyCSR)]
+    pub fn predicate_adjacency_csr(&self, predicate: u32, graph: Option<u32>) -> AdjacencyCSR {
+        let mut edges = self.matching_quads([None, Some(predicate), None, graph])
+            .map(|quad| (quad[0], quad[2]))
+            .collect::<Vec<(u32, u32)>>();
+        edges.sort_unstable();
+        edges.dedup();
+
+        let mut subjects = vec!();
+        let mut row_offsets = vec!();
+
+        for (i, (subject, _)) in edges.iter().enumerate() {
+            if subjects.last() != Some(subject) {
+                subjects.push(*subject);
+                row_offsets.push(i as u32);
+            }
+        }
+        row_offsets.push(edges.len() as u32);
+
+        AdjacencyCSR {
+            subjects: subjects.into_boxed_slice(),
+            row_offsets: row_offsets.into_boxed_slice(),
+            targets: edges.into_iter().map(|(_, object)| object).collect()
+        }
+    }
+}
+
+#[wasm_bindgen(js_class="ForestOfIdentifierQuads")]
+impl ForestOfIdentifierQuads {
+    /// Returns the number of quads of every graph and predicate pair, as a
+    /// flattened list of `[graph, predicate, count]` records sorted by graph
+    /// then by predicate. Pairs that are used by no quad are omitted.
+    #[wasm_bindgen(js_name = countByPredicateAndGraph)]
+    pub fn count_by_predicate_and_graph(&self) -> Box<[u32]> {
+        let mut records = vec!();
+
+        count_by_key(
+            self.matching_quads([None, None, None, None]),
+            self.scan_is_sorted_by(&[3, 1]),
+            |quad| [quad[3], quad[1]],
+            |graph_and_predicate, count| {
+                records.extend_from_slice(&graph_and_predicate);
+                records.push(count);
+            }
+        );
+
+        records.into_boxed_slice()
+    }
+}
+
+#[wasm_bindgen(js_class="ForestOfIdentifierQuads")]
+impl ForestOfIdentifierQuads {
+    /// Returns the nodes of a shortest path from `from` to `to`, both
+    /// included, that follows from subject to object quads whose predicate is
+    /// `predicate`, in the given graph or in any graph. Returns `undefined` if
+    /// there is no such path.
+    ///
+    /// The nodes are explored breadth first, like in `pathExists`, and the
+    /// search stops as soon as `to` is found. If `max_depth` is given, paths
+    /// longer than `max_depth` quads are not explored.
+    #[wasm_bindgen(js_name = shortestPath)]
+    pub fn shortest_path(&self, from: u32, to: u32, predicate: u32, graph: Option<u32>, max_depth: Option<u32>) -> Option<Box<[u32]>> {
+        if from == to {
+            return Some(Box::new([from]));
+        }
+
+        // The node from which each visited node has been reached
+        let mut parents = BTreeMap::new();
+        let mut nodes_to_explore = VecDeque::new();
+        nodes_to_explore.push_back((from, 0));
+
+        let mut found = false;
+
+        'search: while let Some((node, depth)) = nodes_to_explore.pop_front() {
+            if max_depth == Some(depth) {
+                continue;
+            }
+
+            for quad in self.matching_quads([Some(node), Some(predicate), None, graph]) {
+                if quad[2] == from || parents.contains_key(&quad[2]) {
+                    continue;
+                }
+
+                parents.insert(quad[2], node);
+
+                if quad[2] == to {
+                    found = true;
+                    break 'search;
+                }
+
+                nodes_to_explore.push_back((quad[2], depth + 1));
+            }
+        }
+
+        if !found {
+            return None;
+        }
+
+        let mut path = vec![to];
+        while let Some(parent) = parents.get(path.last().unwrap()) {
+            path.push(*parent);
+        }
+        path.reverse();
+
+        Some(path.into_boxed_slice())
+    }
+}
+
+// ==== Transactions
+
+/// A running transaction
+struct Transaction {
+    /// The quads whose membership has changed since the beginning of the
+    /// transaction, with their former membership
+    changes: Vec<([u32; 4], bool)>,
+    /// Length of the journal when the transaction began, or `None` if the
+    /// journal has been cleared or disabled since then
+    journal_length: Option<usize>,
+    /// The insertion sequence when the transaction began, if it was enabled
+    insertion_sequence: Option<InsertionSequence>
+}
+
+#[wasm_bindgen(js_class="ForestOfIdentifierQuads")]
+impl ForestOfIdentifierQuads {
+    /// Starts a transaction: the modifications made until `commit` or
+    /// `rollback` is called can be reverted.
+    ///
+    /// Every quad added or removed during the transaction is kept in memory
+    /// until it ends, and so is a copy of the insertion sequence if it is
+    /// enabled. Nested transactions are not supported.
+    #[wasm_bindgen(js_name = beginTransaction)]
+    pub fn begin_transaction(&mut self) -> Result<(), JsValue> {
+        if self.transaction.is_some() {
+            return Err(JsValue::from_str("A transaction is already running"));
+        }
+
+        self.transaction = Some(Transaction {
+            changes: vec!(),
+            journal_length: Some(self.journal.as_ref().map(|journal| journal.len()).unwrap_or(0)),
+            insertion_sequence: self.insertion_sequence.clone()
+        });
+        Ok(())
+    }
+
+    /// Ends the running transaction and keeps its modifications
+    #[wasm_bindgen]
+    pub fn commit(&mut self) -> Result<(), JsValue> {
+        match self.transaction.take() {
+            Some(_) => Ok(()),
+            None => Err(JsValue::from_str("No transaction is running"))
+        }
+    }
+
+    /// Ends the running transaction and reverts its modifications.
+    ///
+    /// The quads are restored in the trees directly, so the removed quads are
+    /// inserted back even if they contain the sentinel set during the
+    /// transaction, see `setStrictSentinel`. The journal and the insertion
+    /// sequence go back to their state at the beginning of the transaction.
+    ///
+    /// If the journal has been cleared or disabled during the transaction,
+    /// some of its records may have been exported already: the reverted
+    /// modifications are then recorded in the journal like any other.
+    #[wasm_bindgen]
+    pub fn rollback(&mut self) -> Result<(), JsValue> {
+        let transaction = self.transaction
+            .take()
+            .ok_or_else(|| JsValue::from_str("No transaction is running"))?;
+
+        for (quad, was_present) in transaction.changes.into_iter().rev() {
+            if was_present {
+                self.trees.insert(quad);
+            } else {
+                self.trees.delete(quad);
+            }
+
+            self.update_derived_state(quad, !was_present);
+
+            if transaction.journal_length.is_none() {
+                if let Some(journal) = self.journal.as_mut() {
+                    journal.push(if was_present { JOURNAL_ADD } else { JOURNAL_REMOVE });
+                    journal.extend_from_slice(&quad);
+                }
+            }
+        }
+
+        if let (Some(journal), Some(journal_length)) = (self.journal.as_mut(), transaction.journal_length) {
+            journal.truncate(journal_length);
+        }
+
+        // An insertion sequence enabled during the transaction numbers the
+        // quads that are in the dataset now
+        if self.insertion_sequence.is_some() {
+            self.insertion_sequence = transaction.insertion_sequence;
+
+            if self.insertion_sequence.is_none() {
+                self.enable_insertion_sequence();
+            }
+        }
+
+        Ok(())
+    }
+}
+
+// ==== Journal
+
+/// Operation code of an added quad in the journal
+const JOURNAL_ADD: u32 = 0;
+/// Operation code of a removed quad in the journal
+const JOURNAL_REMOVE: u32 = 1;
+
+impl ForestOfIdentifierQuads {
+    /// Tells the running transaction, if any, that the journal no longer
+    /// contains all the records made since it began
+    fn forget_transaction_journal_length(&mut self) {
+        if let Some(transaction) = self.transaction.as_mut() {
+            transaction.journal_length = None;
+        }
+    }
+}
+
+#[wasm_bindgen(js_class="ForestOfIdentifierQuads")]
+impl ForestOfIdentifierQuads {
+    /// Starts recording the modifications of the dataset in the journal.
+    ///
+    /// Only the actual modifications are recorded: adding a quad that is
+    /// already in the dataset or removing a quad that is not in it is not
+    /// recorded.
+    #[wasm_bindgen(js_name = enableJournal)]
+    pub fn enable_journal(&mut self) {
+        if self.journal.is_none() {
+            self.journal = Some(vec!());
+        }
+    }
+
+    /// Stops recording the modifications and drops the journal
+    #[wasm_bindgen(js_name = disableJournal)]
+    pub fn disable_journal(&mut self) {
+        if self.journal.take().is_some() {
+            self.forget_transaction_journal_length();
+        }
+    }
+
+    /// Returns the journal as a list of `[op, s, p, o, g]` records, in the
+    /// order of the modifications. `op` is 0 if the quad has been added and 1
+    /// if it has been removed.
+    ///
+    /// If the journal is not enabled, an empty list is returned.
+    #[wasm_bindgen(js_name = exportJournal)]
+    pub fn export_journal(&self) -> Box<[u32]> {
+        match self.journal.as_ref() {
+            Some(journal) => journal.clone().into_boxed_slice(),
+            None => Box::new([])
+        }
+    }
+
+    /// Empties the journal, for example after it has been exported
+    #[wasm_bindgen(js_name = clearJournal)]
+    pub fn clear_journal(&mut self) {
+        if let Some(journal) = self.journal.as_mut() {
+            journal.clear();
+            self.forget_transaction_journal_length();
+        }
+    }
+
+    /// Applies the modifications of a journal exported with `exportJournal`
+    #[wasm_bindgen(js_name = applyJournal)]
+    pub fn apply_journal(&mut self, journal: &[u32]) -> Result<(), JsValue> {
+        if journal.len() % 5 != 0 {
+            return Err(JsValue::from_str("The length of a journal must be a multiple of 5"));
+        }
+
+        if journal.chunks(5).any(|record| record[0] != JOURNAL_ADD && record[0] != JOURNAL_REMOVE) {
+            return Err(JsValue::from_str("Unknown journal operation"));
+        }
+
+        for record in journal.chunks(5) {
+            let quad = [record[1], record[2], record[3], record[4]];
+
+            if record[0] == JOURNAL_ADD {
+                self.insert_quad(quad);
+            } else {
+                self.delete_quad(quad);
+            }
+        }
+
+        Ok(())
+    }
+}
+
+#[wasm_bindgen(js_class="ForestOfIdentifierQuads")]
+impl ForestOfIdentifierQuads {
+    /// Marks the current size of the dataset as the reference of
+    /// `netChangeSinceSnapshot`
+    #[wasm_bindgen(js_name = markSnapshot)]
+    pub fn mark_snapshot(&mut self) {
+        self.snapshot_size = self.size();
+    }
+
+    /// Returns the number of quads added minus the number of quads removed
+    /// since the last call to `markSnapshot`, or since the creation of the
+    /// dataset if it has never been called
+    #[wasm_bindgen(js_name = netChangeSinceSnapshot)]
+    pub fn net_change_since_snapshot(&self) -> i64 {
+        self.size() as i64 - self.snapshot_size as i64
+    }
+}
+
+// ==== Insertion sequence
+
+/// Numbers the quads in the order they are inserted
+#[derive(Clone)]
+struct InsertionSequence {
+    /// Sequence number of the next inserted quad
+    next_sequence_number: u64,
+    /// The quads of the dataset by sequence number
+    quads: BTreeMap<u64, [u32; 4]>,
+    /// The sequence number of every quad of the dataset
+    sequence_numbers: HashMap<[u32; 4], u64>
+}
+
+impl InsertionSequence {
+    fn new() -> Self {
+        Self { next_sequence_number: 0, quads: BTreeMap::new(), sequence_numbers: HashMap::new() }
+    }
+
+    fn insert(&mut self, quad: [u32; 4]) {
+        let sequence_number = self.next_sequence_number;
+        self.next_sequence_number += 1;
+
+        self.quads.insert(sequence_number, quad);
+        self.sequence_numbers.insert(quad, sequence_number);
+    }
+
+    fn remove(&mut self, quad: &[u32; 4]) {
+        if let Some(sequence_number) = self.sequence_numbers.remove(quad) {
+            self.quads.remove(&sequence_number);
+        }
+    }
+}
+
+#[wasm_bindgen(js_class="ForestOfIdentifierQuads")]
+impl ForestOfIdentifierQuads {
+    /// Starts numbering the inserted quads, see `quadsSince`. The quads that
+    /// are already in the dataset are numbered first.
+    #[wasm_bindgen(js_name = enableInsertionSequence)]
+    pub fn enable_insertion_sequence(&mut self) {
+        if self.insertion_sequence.is_some() {
+            return;
+        }
+
+        let mut insertion_sequence = InsertionSequence::new();
+        for quad in self.matching_quads([None, None, None, None]) {
+            insertion_sequence.insert(quad);
+        }
+
+        self.insertion_sequence = Some(insertion_sequence);
+    }
+
+    /// Stops numbering the inserted quads and forgets their numbers
+    #[wasm_bindgen(js_name = disableInsertionSequence)]
+    pub fn disable_insertion_sequence(&mut self) {
+        self.insertion_sequence = None;
+    }
+
+    /// Returns the sequence number that the next inserted quad will have, or
+    /// 0 if the insertion sequence is not enabled
+    #[wasm_bindgen(js_name = currentWatermark)]
+    pub fn current_watermark(&self) -> u64 {
+        self.insertion_sequence
+            .as_ref()
+            .map(|insertion_sequence| insertion_sequence.next_sequence_number)
+            .unwrap_or(0)
+    }
+
+    /// Returns, flattened and in insertion order, the quads of the dataset
+    /// whose sequence number is greater than or equal to `watermark`.
+    ///
+    /// Removed quads are not returned, but removing a quad does not change
+    /// the sequence numbers of the other ones. A quad that is removed and then
+    /// inserted again gets a new number. If the insertion sequence is not
+    /// enabled, an empty list is returned.
+    #[wasm_bindgen(js_name = quadsSince)]
+    pub fn quads_since(&self, watermark: u64) -> Box<[u32]> {
+        match self.insertion_sequence.as_ref() {
+            Some(insertion_sequence) => insertion_sequence.quads
+                .range(watermark..)
+                .flat_map(|(_, quad)| quad.iter().copied())
+                .collect(),
+            None => Box::new([])
+        }
+    }
+}
+
+// ==== Term statistics
+
+/// Counts, for each position, the number of quads that use each term
+struct TermStatistics {
+    uses: [HashMap<u32, u32>; 4]
+}
+
+impl TermStatistics {
+    fn new() -> Self {
+        Self { uses: Default::default() }
+    }
+
+    fn insert(&mut self, quad: &[u32; 4]) {
+        for (position_uses, term) in self.uses.iter_mut().zip(quad.iter()) {
+            *position_uses.entry(*term).or_insert(0) += 1;
+        }
+    }
+
+    fn remove(&mut self, quad: &[u32; 4]) {
+        for (position_uses, term) in self.uses.iter_mut().zip(quad.iter()) {
+            if let Some(uses) = position_uses.get_mut(term) {
+                *uses -= 1;
+                if *uses == 0 {
+                    position_uses.remove(term);
+                }
+            }
+        }
+    }
+
+    fn cardinalities(&self) -> [usize; 4] {
+        [self.uses[0].len(), self.uses[1].len(), self.uses[2].len(), self.uses[3].len()]
+    }
+}
+
+#[wasm_bindgen(js_class="ForestOfIdentifierQuads")]
+impl ForestOfIdentifierQuads {
+    /// Starts maintaining, for each position, the number of quads that use
+    /// each term, which are updated on every insertion and deletion. They make
+    /// `distinctTermCounts` and `estimateMatchCount` read the number of
+    /// distinct terms instead of scanning the dataset after each modification.
+    ///
+    /// Each distinct term of each position costs one hash map entry of two
+    /// `u32`, so up to 4 entries per distinct identifier.
+    #[wasm_bindgen(js_name = enableStatistics)]
+    pub fn enable_statistics(&mut self) {
+        if self.statistics.is_some() {
+            return;
+        }
+
+        let mut statistics = TermStatistics::new();
+        for quad in self.matching_quads([None, None, None, None]) {
+            statistics.insert(&quad);
+        }
+
+        self.statistics = Some(statistics);
+    }
+
+    /// Stops maintaining the statistics and frees them
+    #[wasm_bindgen(js_name = disableStatistics)]
+    pub fn disable_statistics(&mut self) {
+        self.statistics = None;
+    }
+
+    /// Returns `[subjects, predicates, objects, graphs]`, the number of
+    /// distinct terms at each position.
+    ///
+    /// If the statistics are enabled, they are read without scanning the
+    /// dataset. Else, the dataset is scanned once until it is modified.
+    #[wasm_bindgen(js_name = distinctTermCounts)]
+    pub fn distinct_term_counts(&self) -> Box<[u32]> {
+        self.term_cardinalities().iter().map(|cardinality| *cardinality as u32).collect()
+    }
+}
+
+// ==== Basic graph pattern evaluation
+
+/// Value used in the patterns of the binding functions for the positions that
+/// are variables
+const VARIABLE: i32 = -1;
+
+/// Converts a pattern in which the variables are `VARIABLE` into a pattern
+/// in which they are `None`. An error is returned if the pattern contains
+/// another negative value.
+fn to_spog_pattern(pattern: [i32; 4]) -> Result<[Option<u32>; 4], JsValue> {
+    if let Some(value) = pattern.iter().find(|value| **value < VARIABLE) {
+        return Err(JsValue::from_str(&format!("Invalid pattern value {}: variables are written -1", value)));
+    }
+
+    let to_term = |value: i32| if value == VARIABLE { None } else { Some(value as u32) };
+    Ok([to_term(pattern[0]), to_term(pattern[1]), to_term(pattern[2]), to_term(pattern[3])])
+}
+
+/// Accumulates the solution rows of the binding functions
+struct RowCollector {
+    /// The rows already collected if duplicates have to be removed
+    distinct_rows: Option<BTreeSet<Vec<u32>>>,
+    /// Maximum number of rows, 0 for no limit
+    limit: usize,
+    number_of_rows: usize,
+    values: Vec<u32>
+}
+
+impl RowCollector {
+    fn new(distinct: bool, limit: usize) -> Self {
+        Self {
+            distinct_rows: if distinct { Some(BTreeSet::new()) } else { None },
+            limit,
+            number_of_rows: 0,
+            values: vec!()
+        }
+    }
+
+    /// Returns true if no more rows can be added
+    fn is_full(&self) -> bool {
+        self.limit != 0 && self.number_of_rows >= self.limit
+    }
+
+    /// Adds the row if the limit is not reached and it is not a duplicate
+    /// of a previous row when duplicates are removed
+    fn push(&mut self, row: Vec<u32>) {
+        if self.is_full() {
+            return;
+        }
+
+        if let Some(distinct_rows) = self.distinct_rows.as_mut() {
+            if distinct_rows.contains(&row) {
+                return;
+            }
+
+            distinct_rows.insert(row.clone());
+        }
+
+        self.values.extend(row);
+        self.number_of_rows += 1;
+    }
+
+    fn into_rows(self) -> Box<[u32]> {
+        self.values.into_boxed_slice()
+    }
+}
+
+#[wasm_bindgen(js_class="ForestOfIdentifierQuads")]
 impl ForestOfIdentifierQuads {
-    #[wasm_bindgen]
-    pub fn union(&self, other: &ForestOfIdentifierQuads) -> Self {
-        if Self::are_trivially_mergeable_trees(self, other) {
-            Self::new_tree_from_fusion(self.trees.base_tree.1.union(&other.trees.base_tree.1))
+    /// Returns the values bound to the variables of the pattern by the
+    /// matching quads.
+    ///
+    /// A position of the pattern is either the identifier of a term, or -1 for
+    /// a variable. For each matching quad, the terms at the variable positions
+    /// are returned in the subject, predicate, object, graph order, so the
+    /// number of variables in the pattern gives the size of each row.
+    ///
+    /// If `distinct` is true, duplicated rows are only returned once. If
+    /// `limit` is not 0, at most `limit` rows are returned and the evaluation
+    /// stops as soon as they are found.
+    ///
+    /// An error is thrown if a position is a negative value other than -1.
+    #[wasm_bindgen(js_name = matchBindings)]
+    pub fn match_bindings(&self, s: i32, p: i32, o: i32, g: i32, distinct: bool, limit: usize) -> Result<Box<[u32]>, JsValue> {
+        let spog = to_spog_pattern([s, p, o, g])?;
+        let variables = (0..4).filter(|&i| spog[i].is_none()).collect::<Vec<usize>>();
+
+        let mut rows = RowCollector::new(distinct, limit);
+        for quad in self.matching_quads(spog) {
+            if rows.is_full() {
+                break;
+            }
+
+            rows.push(variables.iter().map(|&i| quad[i]).collect());
+        }
+
+        Ok(rows.into_rows())
+    }
+}
+
+impl ForestOfIdentifierQuads {
+    /// Converts a pattern received from Javascript with `to_spog_pattern`
+    fn read_pattern(pattern: &[i32]) -> Result<[Option<u32>; 4], JsValue> {
+        if pattern.len() != 4 {
+            return Err(JsValue::from_str("A pattern must contain 4 values"));
+        }
+
+        to_spog_pattern([pattern[0], pattern[1], pattern[2], pattern[3]])
+    }
+}
+
+#[wasm_bindgen(js_class="ForestOfIdentifierQuads")]
+impl ForestOfIdentifierQuads {
+    /// Joins two patterns that share one variable, and returns the solutions.
+    ///
+    /// Patterns are written like in `matchBindings`. The shared variable is at
+    /// the position `first_join_position` (0 for subject, 1 for predicate, 2
+    /// for object and 3 for graph) in the first pattern and
+    /// `second_join_position` in the second one.
+    ///
+    /// Each solution row contains the values of the variables of the first
+    /// pattern, and then the values of the variables of the second pattern
+    /// except the shared one, both in subject, predicate, object, graph order.
+    ///
+    /// The pattern with the less estimated matches, see `estimateMatchCount`,
+    /// is evaluated first, and for each of its matches, the other pattern is
+    /// evaluated with the shared variable bound. The patterns are not searched
+    /// to choose: the estimate only needs the number of distinct terms at each
+    /// position, which is read from the statistics if they are enabled, or
+    /// computed once until the dataset is modified.
+    ///
+    /// `distinct` and `limit` have the same meaning as in `matchBindings`.
+    #[wasm_bindgen(js_name = joinTwoPatterns)]
+    pub fn join_two_patterns(&self, first: &[i32], first_join_position: usize,
+        second: &[i32], second_join_position: usize,
+        distinct: bool, limit: usize) -> Result<Box<[u32]>, JsValue> {
+        let first = Self::read_pattern(first)?;
+        let second = Self::read_pattern(second)?;
+
+        if first_join_position >= 4 || first[first_join_position].is_some()
+            || second_join_position >= 4 || second[second_join_position].is_some() {
+            return Err(JsValue::from_str("The join positions must be variables of the patterns"));
+        }
+
+        let first_variables = (0..4).filter(|&i| first[i].is_none()).collect::<Vec<usize>>();
+        let second_variables = (0..4)
+            .filter(|&i| second[i].is_none() && i != second_join_position)
+            .collect::<Vec<usize>>();
+
+        let estimate = |spog: [Option<u32>; 4]| self.estimate_match_count(spog[0], spog[1], spog[2], spog[3]);
+        let first_is_outer = estimate(first) <= estimate(second);
+        let (outer, outer_join_position, inner, inner_join_position) = if first_is_outer {
+            (first, first_join_position, second, second_join_position)
         } else {
-            let mut new_tree = Self::new();
+            (second, second_join_position, first, first_join_position)
+        };
+
+        let mut rows = RowCollector::new(distinct, limit);
+
+        'outer: for outer_quad in self.matching_quads(outer) {
+            let mut bound_inner = inner;
+            bound_inner[inner_join_position] = Some(outer_quad[outer_join_position]);
+
+            for inner_quad in self.matching_quads(bound_inner) {
+                if rows.is_full() {
+                    break 'outer;
+                }
+
+                let (first_quad, second_quad) = if first_is_outer {
+                    (outer_quad, inner_quad)
+                } else {
+                    (inner_quad, outer_quad)
+                };
 
-            for quad in self.trees.filter([None, None, None, None]) {
-                new_tree.add(quad[0], quad[1], quad[2], quad[3]);
+                rows.push(
+                    first_variables.iter().map(|&i| first_quad[i])
+                        .chain(second_variables.iter().map(|&i| second_quad[i]))
+                        .collect()
+                );
             }
+        }
+
+        Ok(rows.into_rows())
+    }
+}
+
+#[wasm_bindgen(js_class="ForestOfIdentifierQuads")]
+impl ForestOfIdentifierQuads {
+    /// Returns the objects of the subject for each of the given predicates,
+    /// in the given graph or in any graph.
+    ///
+    /// The returned list is `[p1, n1, o1_1, ..., o1_n1, p2, n2, ...]`: for each
+    /// predicate in the order of `predicates`, the predicate, the number of
+    /// objects and the objects. An object is repeated if it is found in
+    /// several graphs.
+    ///
+    /// The quads of the subject are scanned once.
+    #[wasm_bindgen(js_name = starJoin)]
+    pub fn star_join(&self, subject: u32, predicates: &[u32], graph: Option<u32>) -> Box<[u32]> {
+        let mut objects_by_predicate = predicates
+            .iter()
+            .map(|&predicate| (predicate, vec!()))
+            .collect::<BTreeMap<u32, Vec<u32>>>();
 
-            for quad in other.trees.filter([None, None, None, None]) {
-                new_tree.add(quad[0], quad[1], quad[2], quad[3]);
+        for quad in self.matching_quads([Some(subject), None, None, graph]) {
+            if let Some(objects) = objects_by_predicate.get_mut(&quad[1]) {
+                objects.push(quad[2]);
             }
-            
-            new_tree
         }
+
+        let mut result = vec!();
+        for predicate in predicates {
+            let objects = &objects_by_predicate[predicate];
+            result.push(*predicate);
+            result.push(objects.len() as u32);
+            result.extend_from_slice(objects);
+        }
+
+        result.into_boxed_slice()
     }
 }
 
+// ==== Inference
+
 #[wasm_bindgen(js_class="ForestOfIdentifierQuads")]
 impl ForestOfIdentifierQuads {
-    #[wasm_bindgen]
-    pub fn difference(&self, other: &ForestOfIdentifierQuads) -> Self {
-        if Self::are_trivially_mergeable_trees(self, other) {
-            Self::new_tree_from_fusion(self.trees.base_tree.1.difference(&other.trees.base_tree.1))
-        } else {
-            let mut new_tree = Self::new();
+    /// Adds, for every quad `(s, predicate, o, g)`, the quad
+    /// `(o, inverse_predicate, s, g)`
+    #[wasm_bindgen(js_name = materializeInverse)]
+    pub fn materialize_inverse(&mut self, predicate: u32, inverse_predicate: u32) {
+        let quads = self.matching_quads([None, Some(predicate), None, None]).collect::<Vec<[u32; 4]>>();
+
+        for quad in quads {
+            self.add(quad[2], inverse_predicate, quad[0], quad[3]);
+        }
+    }
+}
+
+#[wasm_bindgen(js_class="ForestOfIdentifierQuads")]
+impl ForestOfIdentifierQuads {
+    /// Adds, for every quad `(s, predicate, o, g)`, the quad
+    /// `(o, predicate, s, g)`, and returns the number of added quads
+    #[wasm_bindgen(js_name = symmetricClosure)]
+    pub fn symmetric_closure(&mut self, predicate: u32) -> usize {
+        let quads = self.matching_quads([None, Some(predicate), None, None]).collect::<Vec<[u32; 4]>>();
+
+        quads
+            .into_iter()
+            .filter(|quad| self.insert_quad([quad[2], quad[1], quad[0], quad[3]]))
+            .count()
+    }
+}
+
+// ==== Serialization
+
+#[wasm_bindgen(js_class="ForestOfIdentifierQuads")]
+impl ForestOfIdentifierQuads {
+    /// Writes the dataset in the N-Quads format.
+    ///
+    /// `resolver` is called with an identifier and must return the N-Quads
+    /// representation of the term, already escaped. It is called once per
+    /// distinct identifier. The graph of the quads in the default graph, see
+    /// `defaultGraphId`, is omitted.
+    #[wasm_bindgen(js_name = toNQuads)]
+    pub fn to_nquads(&self, resolver: &js_sys::Function) -> Result<String, JsValue> {
+        let mut terms = BTreeMap::<u32, String>::new();
+        let mut resolve = |identifier: u32| -> Result<String, JsValue> {
+            if let Some(term) = terms.get(&identifier) {
+                return Ok(term.clone());
+            }
+
+            let term = resolver
+                .call1(&JsValue::NULL, &JsValue::from(identifier))?
+                .as_string()
+                .ok_or_else(|| JsValue::from_str("The resolver must return a string"))?;
+            terms.insert(identifier, term.clone());
+            Ok(term)
+        };
+
+        let mut nquads = String::new();
+
+        for quad in self.matching_quads([None, None, None, None]) {
+            for &identifier in &quad[0..3] {
+                nquads.push_str(&resolve(identifier)?);
+                nquads.push(' ');
+            }
+
+            if quad[3] != self.default_graph_id {
+                nquads.push_str(&resolve(quad[3])?);
+                nquads.push(' ');
+            }
+
+            nquads.push_str(".\n");
+        }
+
+        Ok(nquads)
+    }
+}
+
+/// Reads the JSON arrays written by `toJSON`
+struct JsonQuadReader<'a> {
+    json: &'a [u8],
+    position: usize
+}
+
+impl<'a> JsonQuadReader<'a> {
+    fn new(json: &'a str) -> Self {
+        Self { json: json.as_bytes(), position: 0 }
+    }
+
+    fn error(&self, message: &str) -> JsValue {
+        JsValue::from_str(&format!("Invalid JSON at position {}: {}", self.position, message))
+    }
+
+    /// Skips the whitespaces and returns the next byte without consuming it
+    fn peek(&mut self) -> Option<u8> {
+        while self.position < self.json.len() && self.json[self.position].is_ascii_whitespace() {
+            self.position += 1;
+        }
+
+        self.json.get(self.position).copied()
+    }
+
+    /// Consumes the next byte, that must be `expected`
+    fn expect(&mut self, expected: u8) -> Result<(), JsValue> {
+        if self.peek() != Some(expected) {
+            return Err(self.error(&format!("expected '{}'", expected as char)));
+        }
+
+        self.position += 1;
+        Ok(())
+    }
+
+    /// Consumes the next byte if it is `byte`
+    fn accept(&mut self, byte: u8) -> bool {
+        let accepted = self.peek() == Some(byte);
+        if accepted {
+            self.position += 1;
+        }
+        accepted
+    }
+
+    fn read_identifier(&mut self) -> Result<u32, JsValue> {
+        self.peek();
+        let start = self.position;
+        while self.position < self.json.len() && self.json[self.position].is_ascii_digit() {
+            self.position += 1;
+        }
 
-            for quad in self.trees.filter([None, None, None, None]) {
-                if !other.has(quad[0], quad[1], quad[2], quad[3]) {
-                    new_tree.add(quad[0], quad[1], quad[2], quad[3]);
+        std::str::from_utf8(&self.json[start..self.position])
+            .ok()
+            .and_then(|digits| digits.parse::<u32>().ok())
+            .ok_or_else(|| self.error("expected an identifier"))
+    }
+
+    fn read_quad(&mut self) -> Result<[u32; 4], JsValue> {
+        let mut quad = [0; 4];
+
+        self.expect(b'[')?;
+        for (i, identifier) in quad.iter_mut().enumerate() {
+            if i != 0 {
+                self.expect(b',')?;
+            }
+            *identifier = self.read_identifier()?;
+        }
+        self.expect(b']')?;
+
+        Ok(quad)
+    }
+
+    /// Reads the whole JSON document, an array of quads
+    fn read_quads(&mut self) -> Result<Vec<[u32; 4]>, JsValue> {
+        let mut quads = vec!();
+
+        self.expect(b'[')?;
+        if !self.accept(b']') {
+            loop {
+                quads.push(self.read_quad()?);
+
+                if !self.accept(b',') {
+                    break;
                 }
             }
-            
-            new_tree
+            self.expect(b']')?;
+        }
+
+        if self.peek().is_some() {
+            return Err(self.error("expected the end of the document"));
         }
+
+        Ok(quads)
     }
 }
 
 #[wasm_bindgen(js_class="ForestOfIdentifierQuads")]
 impl ForestOfIdentifierQuads {
-    #[wasm_bindgen]
-    pub fn contains(&self, other: &ForestOfIdentifierQuads) -> bool {
-        if Self::are_trivially_mergeable_trees(self, other) {
-            self.trees.base_tree.1.is_superset(&other.trees.base_tree.1)
+    /// Writes the dataset as a JSON array of `[s, p, o, g]` arrays
+    #[wasm_bindgen(js_name = toJSON)]
+    pub fn to_json(&self) -> String {
+        let quads = self.matching_quads([None, None, None, None])
+            .map(|quad| format!("[{},{},{},{}]", quad[0], quad[1], quad[2], quad[3]))
+            .collect::<Vec<String>>();
+
+        format!("[{}]", quads.join(","))
+    }
+
+    /// Builds a dataset from the JSON written by `toJSON`. An error is thrown
+    /// if the JSON is malformed.
+    #[wasm_bindgen(js_name = fromJSON)]
+    pub fn from_json(json: &str) -> Result<ForestOfIdentifierQuads, JsValue> {
+        let quads = JsonQuadReader::new(json).read_quads()?;
+
+        let mut forest = Self::new();
+        for quad in quads {
+            forest.trees.insert(quad);
+        }
+
+        Ok(forest)
+    }
+}
+
+#[wasm_bindgen(js_class="ForestOfIdentifierQuads")]
+impl ForestOfIdentifierQuads {
+    /// Writes the quads in a binary format in which they are sorted, see
+    /// `fromBytesSorted`.
+    ///
+    /// The first 4 bytes are the positions of the terms in the order of the
+    /// base tree (0 for the subject to 3 for the graph). They are followed by
+    /// the quads, sorted in this order, as four little endian `u32`.
+    #[wasm_bindgen(js_name = toBytesSorted)]
+    pub fn to_bytes_sorted(&self) -> Box<[u8]> {
+        let order = self.base_order();
+
+        let mut bytes = Vec::with_capacity(4 + self.size() * 16);
+        bytes.extend(order.iter().map(|position| *position as u8));
+
+        let mut write_quad = |quad: [u32; 4]| {
+            for position in order.iter() {
+                bytes.extend_from_slice(&quad[*position].to_le_bytes());
+            }
+        };
+
+        if self.scan_is_sorted_by(&order) {
+            self.matching_quads([None, None, None, None]).for_each(&mut write_quad);
         } else {
-            for quad in other.trees.filter([None, None, None, None]) {
-                if !self.has(quad[0], quad[1], quad[2], quad[3]) {
-                    return false;
-                }
+            // The frozen list is sorted in SPOG order
+            self.sorted_matching_quads([None, None, None, None], order).into_iter().for_each(&mut write_quad);
+        }
+
+        bytes.into_boxed_slice()
+    }
+
+    /// Builds a dataset from the bytes written by `toBytesSorted`, and
+    /// freezes it if `freeze` is true.
+    ///
+    /// Loading the bytes is not faster than loading unsorted quads: the blocks
+    /// of a tree can only be built by identifier_forest, so the quads are
+    /// inserted one by one in the base tree, in O(n log n). Freezing the
+    /// dataset sorts the quads once more, as the list of a frozen dataset is
+    /// sorted in SPOG order and the base tree is in OGPS order.
+    ///
+    /// An error is thrown if the bytes are malformed, if they were written
+    /// with another base order, or if the quads are not sorted.
+    #[wasm_bindgen(js_name = fromBytesSorted)]
+    pub fn from_bytes_sorted(bytes: &[u8], freeze: bool) -> Result<ForestOfIdentifierQuads, JsValue> {
+        let mut forest = Self::new();
+        let order = forest.base_order();
+
+        if bytes.len() < 4 || (bytes.len() - 4) % 16 != 0 {
+            return Err(JsValue::from_str("fromBytesSorted: the number of bytes is not valid"));
+        }
+
+        if bytes[0..4].iter().zip(order.iter()).any(|(byte, position)| *byte as usize != *position) {
+            return Err(JsValue::from_str("fromBytesSorted: the quads were not written in the base order"));
+        }
+
+        let mut previous_key = None;
+        for chunk in bytes[4..].chunks(16) {
+            let mut key = [0u32; 4];
+            for (value, term_bytes) in key.iter_mut().zip(chunk.chunks(4)) {
+                *value = u32::from_le_bytes([term_bytes[0], term_bytes[1], term_bytes[2], term_bytes[3]]);
+            }
+
+            if matches!(previous_key, Some(previous_key) if previous_key >= key) {
+                return Err(JsValue::from_str("fromBytesSorted: the quads are not sorted"));
+            }
+            previous_key = Some(key);
+
+            let mut quad = [0u32; 4];
+            for (value, position) in key.iter().zip(order.iter()) {
+                quad[*position] = *value;
             }
-            
-            true
+
+            forest.trees.insert(quad);
         }
+
+        if freeze {
+            forest.freeze();
+        }
+
+        Ok(forest)
     }
 }
 
+// ==== Consistency
+
 #[wasm_bindgen(js_class="ForestOfIdentifierQuads")]
 impl ForestOfIdentifierQuads {
-    #[wasm_bindgen(js_name = containsIdentifierList)]
-    pub fn contains_slice(&self, other: &[u32]) -> bool {
-        assert!(other.len() % 4 == 0);
-        let mut i = 0;
-        while i != other.len() / 4 { 
-            if !self.has(other[i * 4], other[i * 4 + 1], other[i * 4 + 2], other[i * 4 + 3]) {
+    /// Returns true if every built optional tree contains exactly the same
+    /// quads as the base tree.
+    ///
+    /// This is a debugging tool: the trees are supposed to always be
+    /// consistent.
+    #[wasm_bindgen(js_name = checkConsistency)]
+    pub fn check_consistency(&self) -> bool {
+        let size = self.size();
+        let optional_trees = self.trees
+            .optional_trees
+            .iter()
+            .filter_map(|pair| pair.1.get().map(|tree| (&pair.0, tree)))
+            .collect::<Vec<_>>();
+
+        if optional_trees.iter().any(|(_, tree)| tree.len() != size) {
+            return false;
+        }
+
+        // As all trees have the same size, they contain the same quads if
+        // every quad of one of them is contained by the others
+        let mut number_of_quads = 0;
+
+        for quad in self.matching_quads([None, None, None, None]) {
+            number_of_quads += 1;
+
+            if !self.has(quad[0], quad[1], quad[2], quad[3]) {
+                return false;
+            }
+
+            if optional_trees.iter().any(|(order, tree)| !order.contains(tree, &quad)) {
                 return false;
             }
-            i = i + 1;
         }
 
-        true
+        number_of_quads == size
     }
 }
 
 #[wasm_bindgen(js_class="ForestOfIdentifierQuads")]
 impl ForestOfIdentifierQuads {
-    #[wasm_bindgen(js_name = equalsIdentifierList)]
-    pub fn equals_slice(&self, other: &[u32]) -> bool {
-        if self.trees.base_tree.1.len() != other.len() / 4 {
-            return false;
+    /// Drops everything that is derived from the base tree and rebuilds it
+    /// from the quads of the base tree: the optional trees, which are built
+    /// again when a query needs them, the list of a frozen dataset, the
+    /// membership index and the term statistics, see `enableStatistics`.
+    ///
+    /// The quads of the dataset do not change. If `checkConsistency` returns
+    /// false, this makes the dataset consistent again.
+    #[wasm_bindgen(js_name = rebuildFromBase)]
+    pub fn rebuild_from_base(&mut self) {
+        for optional_tree_tuple in self.trees.optional_trees.iter_mut() {
+            optional_tree_tuple.1.take();
         }
 
-        self.contains_slice(other)
+        let was_frozen = self.frozen.take().is_some();
+        let had_membership_index = self.membership_index.take().is_some();
+        let had_statistics = self.statistics.take().is_some();
+        self.term_cardinalities.set(None);
+
+        if was_frozen {
+            self.frozen = Some(self.sorted_matching_quads([None, None, None, None], SPOG));
+        }
+
+        if had_membership_index {
+            self.build_membership_index();
+        }
+
+        if had_statistics {
+            self.enable_statistics();
+        }
     }
 }
 
+// ==== Frozen datasets
+
 #[wasm_bindgen(js_class="ForestOfIdentifierQuads")]
 impl ForestOfIdentifierQuads {
+    /// Freezes the dataset: its quads are copied in a list sorted in SPOG
+    /// order, which is used to answer the queries instead of the trees, and
+    /// the optional trees are dropped.
+    ///
+    /// Scanning a contiguous list is faster than scanning a tree, so this is
+    /// intended for datasets that are built once and then read with large
+    /// scans. Patterns whose fixed terms are a prefix of SPOG are found by
+    /// binary search, the others require a full scan. The base tree is kept
+    /// for `has` and the set operations.
+    ///
+    /// The list is a copy of the quads of the base tree, so a frozen dataset
+    /// uses about twice the memory of a dataset with only its base tree: the
+    /// optional trees it drops are only compensated if at least one was
+    /// built. Keeping the base tree lets `thaw` and the modifications unfreeze
+    /// the dataset without rebuilding it.
+    ///
+    /// Modifying the dataset unfreezes it.
     #[wasm_bindgen]
-    pub fn has_same_elements(&self, other: &ForestOfIdentifierQuads) -> bool {
-        if self.trees.base_tree.1.len() != other.trees.base_tree.1.len() {
-            return false;
+    pub fn freeze(&mut self) {
+        if self.frozen.is_some() {
+            return;
         }
 
-        self.contains(other)
+        let quads = self.sorted_matching_quads([None, None, None, None], SPOG);
+
+        for optional_tree_tuple in self.trees.optional_trees.iter_mut() {
+            optional_tree_tuple.1.take();
+        }
+
+        self.frozen = Some(quads);
+    }
+
+    /// Unfreezes the dataset: queries are answered by the trees again
+    #[wasm_bindgen]
+    pub fn thaw(&mut self) {
+        self.frozen = None;
+    }
+
+    /// Returns true if the dataset is frozen
+    #[wasm_bindgen(js_name = isFrozen)]
+    pub fn is_frozen(&self) -> bool {
+        self.frozen.is_some()
     }
 }
 
+// ==== Membership index
+
 #[wasm_bindgen(js_class="ForestOfIdentifierQuads")]
 impl ForestOfIdentifierQuads {
-    /// Counts the number of quads that matches the given pattern
-    #[wasm_bindgen(js_name = matchCount)]
-    pub fn match_count(&self, s: Option<u32>, p: Option<u32>, o: Option<u32>, g: Option<u32>) -> usize {
-        let spog = [s, p, o, g];
-        return self.trees.search_all_matching_quads(spog, true).count();
+    /// Builds a hash set of every quad, which is then used by `has`,
+    /// `containsIdentifierList` and `intersectIdentifierList` and kept up to
+    /// date when the dataset is modified.
+    ///
+    /// Looking for a quad in this set is faster than in a tree, at the cost of
+    /// one more copy of the quads.
+    #[wasm_bindgen(js_name = buildMembershipIndex)]
+    pub fn build_membership_index(&mut self) {
+        if self.membership_index.is_none() {
+            self.membership_index = Some(self.matching_quads([None, None, None, None]).collect());
+        }
+    }
+
+    /// Drops the hash set built by `buildMembershipIndex`
+    #[wasm_bindgen(js_name = dropMembershipIndex)]
+    pub fn drop_membership_index(&mut self) {
+        self.membership_index = None;
+    }
+}
+
+// ==== Identifier compaction
+
+/// The result of `compactIds`: a dataset that uses dense identifiers and the
+/// mapping from the identifiers of the original dataset. The mapping contains
+/// the identifiers used by the quads and the identifier of the default graph.
+#[wasm_bindgen]
+pub struct CompactionResult {
+    dataset: ForestOfIdentifierQuads,
+    /// The original identifiers sorted in increasing order: the new identifier
+    /// of `mapping[i]` is `i`
+    mapping: Box<[u32]>
+}
+
+#[wasm_bindgen]
+impl CompactionResult {
+    /// Returns the identifiers of the original dataset sorted in increasing
+    /// order. The new identifier of an original identifier is its position in
+    /// this list.
+    #[wasm_bindgen(getter)]
+    pub fn mapping(&self) -> Box<[u32]> {
+        self.mapping.clone()
+    }
+
+    /// Returns the compacted dataset. The result can not be used anymore.
+    #[wasm_bindgen(js_name = intoDataset)]
+    pub fn into_dataset(self) -> ForestOfIdentifierQuads {
+        self.dataset
     }
 }
 
 #[wasm_bindgen(js_class="ForestOfIdentifierQuads")]
 impl ForestOfIdentifierQuads {
-    /// Ensures the best tree to search quads matching the given pattern is built
-    #[wasm_bindgen(js_name = ensureHasIndexfor)]
-    pub fn ensure_has_index_for(&mut self, s: bool, p: bool, o: bool, g: bool) {
-        self.trees.ensure_has_index_for(s, p, o, g);
+    /// Builds a dataset in which the `k` distinct identifiers used by this
+    /// dataset, whatever their position is, are renumbered from `0` to `k - 1`.
+    ///
+    /// Identifiers keep their relative order. The identifier of the default
+    /// graph is always in the mapping, even if no quad uses it, so no other
+    /// term is renumbered into the default graph.
+    #[wasm_bindgen(js_name = compactIds)]
+    pub fn compact_ids(&self) -> CompactionResult {
+        let quads = self.matching_quads([None, None, None, None]).collect::<Vec<[u32; 4]>>();
+
+        let mut mapping = quads.iter().flatten().copied().collect::<Vec<u32>>();
+        mapping.push(self.default_graph_id);
+        mapping.sort_unstable();
+        mapping.dedup();
+
+        let new_id = |id: u32| mapping.binary_search(&id).unwrap() as u32;
+
+        let mut dataset = self.new_like();
+        dataset.default_graph_id = new_id(self.default_graph_id);
+
+        for quad in quads {
+            dataset.trees.insert([new_id(quad[0]), new_id(quad[1]), new_id(quad[2]), new_id(quad[3])]);
+        }
+
+        CompactionResult { dataset, mapping: mapping.into_boxed_slice() }
+    }
+}
+
+// ==== Bulk loading
+
+/// A dataset being loaded chunk by chunk, see `beginBulkLoad`
+#[wasm_bindgen]
+pub struct BulkLoader {
+    dataset: ForestOfIdentifierQuads,
+    /// Number of quads added by the pushed chunks
+    number_of_added_quads: usize
+}
+
+#[wasm_bindgen]
+impl BulkLoader {
+    /// Inserts the quads of an identifier list, like
+    /// `insertFromIdentifierList`, and returns the number of quads added by
+    /// all the chunks pushed so far
+    #[wasm_bindgen(js_name = pushChunk)]
+    pub fn push_chunk(&mut self, encoded_quads: &[u32]) -> usize {
+        self.number_of_added_quads += self.dataset.extend_from_slice(encoded_quads);
+        self.number_of_added_quads
+    }
+
+    /// Ends the loading and returns the loaded dataset. The loader can not be
+    /// used anymore.
+    #[wasm_bindgen]
+    pub fn finish(self) -> ForestOfIdentifierQuads {
+        self.dataset
     }
 }
 
 #[wasm_bindgen(js_class="ForestOfIdentifierQuads")]
 impl ForestOfIdentifierQuads {
-    /// Counts the number of living trees
-    #[wasm_bindgen(js_name = getNumberOfLivingTrees)]
-    pub fn get_number_of_living_trees(&self) -> usize {
-        self.trees.get_number_of_living_trees()
+    /// Starts loading quads chunk by chunk, so Javascript can do other work
+    /// between two chunks instead of being blocked by a single large insertion.
+    ///
+    /// The dataset is moved into the returned loader: this object can not be
+    /// used anymore, and `finish` gives the dataset back. The optional trees
+    /// are dropped so only the base tree is updated during the loading; they
+    /// are built again when a query needs them.
+    #[wasm_bindgen(js_name = beginBulkLoad)]
+    pub fn begin_bulk_load(mut self) -> BulkLoader {
+        for optional_tree_tuple in self.trees.optional_trees.iter_mut() {
+            optional_tree_tuple.1.take();
+        }
+
+        BulkLoader { dataset: self, number_of_added_quads: 0 }
     }
 }
@@ -5,7 +5,8 @@
 extern crate wasm_bindgen_test;
 use wasm_bindgen_test::*;
 
-// TODO
+use wasm_bindgen::JsValue;
+use wasm_tree_backend::ForestOfIdentifierQuads;
 
 wasm_bindgen_test_configure!(run_in_browser);
 
@@ -13,3 +14,1599 @@ wasm_bindgen_test_configure!(run_in_browser);
 fn pass() {
     assert_eq!(1 + 1, 2);
 }
+
+/// Groups a flattened identifier list by quads, sorted in SPOG order
+fn sorted_quads(identifier_list: &[u32]) -> Vec<[u32; 4]> {
+    let mut quads = identifier_list
+        .chunks(4)
+        .map(|quad| [quad[0], quad[1], quad[2], quad[3]])
+        .collect::<Vec<[u32; 4]>>();
+    quads.sort();
+    quads
+}
+
+#[wasm_bindgen_test]
+fn rename_graph_merges_quads() {
+    let mut forest = ForestOfIdentifierQuads::new_from_identifier_list(&[
+        1, 2, 3, 10,
+        4, 5, 6, 10,
+        1, 2, 3, 20,
+        7, 8, 9, 30
+    ]);
+
+    forest.rename_graph(10, 20);
+
+    assert_eq!(forest.size(), 3);
+    assert_eq!(forest.match_count(None, None, None, Some(10)), 0);
+    assert_eq!(forest.match_count(None, None, None, Some(20)), 2);
+    assert_eq!(forest.match_count(None, None, None, Some(30)), 1);
+    assert_eq!(
+        sorted_quads(&forest.get_all(None, None, None, Some(20))),
+        vec![[1, 2, 3, 20], [4, 5, 6, 20]]
+    );
+}
+
+#[wasm_bindgen_test]
+fn clear_graph_removes_only_the_graph() {
+    let mut forest = ForestOfIdentifierQuads::new_from_identifier_list(&[
+        1, 2, 3, 10,
+        4, 5, 6, 10,
+        1, 2, 3, 20
+    ]);
+
+    assert_eq!(forest.clear_graph(10), 2);
+    assert_eq!(forest.clear_graph(10), 0);
+    assert_eq!(sorted_quads(&forest.get_all(None, None, None, None)), vec![[1, 2, 3, 20]]);
+}
+
+#[wasm_bindgen_test]
+fn copy_graph_keeps_the_source() {
+    let mut forest = ForestOfIdentifierQuads::new_from_identifier_list(&[
+        1, 2, 3, 10,
+        4, 5, 6, 10,
+        1, 2, 3, 20
+    ]);
+
+    forest.copy_graph(10, 10);
+    assert_eq!(forest.size(), 3);
+
+    forest.copy_graph(10, 20);
+    assert_eq!(
+        sorted_quads(&forest.get_all(None, None, None, None)),
+        vec![[1, 2, 3, 10], [1, 2, 3, 20], [4, 5, 6, 10], [4, 5, 6, 20]]
+    );
+}
+
+#[wasm_bindgen_test]
+fn ask_checks_pattern_existence() {
+    let forest = ForestOfIdentifierQuads::new_from_identifier_list(&[1, 2, 3, 10, 4, 5, 6, 10]);
+
+    assert!(forest.ask(None, None, None, None));
+    assert!(forest.ask(Some(1), None, Some(3), None));
+    assert!(!forest.ask(Some(1), None, Some(6), None));
+    assert!(!ForestOfIdentifierQuads::new().ask(None, None, None, None));
+}
+
+#[wasm_bindgen_test]
+fn first_match_returns_a_matching_quad() {
+    let forest = ForestOfIdentifierQuads::new_from_identifier_list(&[1, 2, 3, 10, 4, 5, 6, 10]);
+
+    assert_eq!(forest.first_match(Some(4), None, None, None).as_deref(), Some(&[4, 5, 6, 10][..]));
+    assert_eq!(forest.first_match(Some(5), None, None, None), None);
+}
+
+#[wasm_bindgen_test]
+fn sample_quads_is_deterministic() {
+    let identifier_list = (0..100).flat_map(|i| vec![i, 1, 2, 3]).collect::<Vec<u32>>();
+    let forest = ForestOfIdentifierQuads::new_from_identifier_list(&identifier_list);
+
+    let sample = forest.sample_quads(10, 42);
+    assert_eq!(sample.len(), 10 * 4);
+    assert_eq!(sample, forest.sample_quads(10, 42));
+    assert!(forest.contains_slice(&sample));
+
+    assert_eq!(sorted_quads(&forest.sample_quads(1000, 7)), sorted_quads(&identifier_list));
+}
+
+#[wasm_bindgen_test]
+fn last_match_returns_a_matching_quad() {
+    let forest = ForestOfIdentifierQuads::new_from_identifier_list(&[1, 2, 3, 10, 4, 5, 6, 10]);
+
+    assert_eq!(forest.last_match(None, None, Some(3), None).as_deref(), Some(&[1, 2, 3, 10][..]));
+    assert_eq!(forest.last_match(Some(5), None, None, None), None);
+}
+
+#[wasm_bindgen_test]
+fn last_match_of_a_frozen_dataset_is_the_greatest_in_spog_order() {
+    let mut forest = ForestOfIdentifierQuads::new_from_identifier_list(&[
+        1, 2, 3, 10, 1, 2, 4, 10, 1, 5, 3, 20, 1, 7, 3, 10, 4, 5, 6, 10
+    ]);
+    forest.freeze();
+
+    assert_eq!(forest.last_match(Some(1), None, None, None).as_deref(), Some(&[1, 7, 3, 10][..]));
+    assert_eq!(forest.last_match(Some(1), None, Some(3), Some(20)).as_deref(), Some(&[1, 5, 3, 20][..]));
+    assert_eq!(forest.last_match(None, None, None, None).as_deref(), Some(&[4, 5, 6, 10][..]));
+    assert_eq!(forest.last_match(Some(1), None, Some(6), None), None);
+}
+
+#[wasm_bindgen_test]
+fn write_all_into_is_capped_by_the_capacity() {
+    let forest = ForestOfIdentifierQuads::new_from_identifier_list(&[1, 2, 3, 10, 4, 5, 6, 10]);
+
+    let mut buffer = [0u32; 11];
+    assert_eq!(forest.write_all_into(None, None, None, None, &mut buffer), 2);
+    assert_eq!(sorted_quads(&buffer[0..8]), vec![[1, 2, 3, 10], [4, 5, 6, 10]]);
+
+    let mut small_buffer = [0u32; 7];
+    assert_eq!(forest.write_all_into(None, None, None, None, &mut small_buffer), 1);
+    assert_eq!(small_buffer[4..7], [0, 0, 0]);
+}
+
+#[wasm_bindgen_test]
+fn max_living_trees_is_respected() {
+    let mut forest = ForestOfIdentifierQuads::new_from_identifier_list(&[
+        1, 2, 3, 10,
+        4, 5, 6, 10,
+        1, 5, 3, 20
+    ]);
+
+    forest.set_max_living_trees(2);
+
+    for &(s, p, o, g) in &[(true, false, false, false), (false, true, false, false),
+                           (false, false, false, true), (true, true, false, false)] {
+        forest.ensure_has_index_for(s, p, o, g);
+        assert!(forest.get_number_of_living_trees() <= 2);
+    }
+
+    assert_eq!(forest.match_count(Some(1), None, None, None), 2);
+    assert_eq!(forest.match_count(None, Some(5), None, None), 2);
+    assert_eq!(forest.match_count(None, None, None, Some(20)), 1);
+    assert_eq!(forest.match_count(None, Some(2), None, Some(10)), 1);
+    assert!(forest.get_number_of_living_trees() <= 2);
+
+    forest.set_max_living_trees(1);
+    assert_eq!(forest.get_number_of_living_trees(), 1);
+    assert_eq!(forest.match_count(Some(4), None, None, None), 1);
+}
+
+#[wasm_bindgen_test]
+fn only_index_requests_evict_the_least_recently_used_tree() {
+    let mut forest = ForestOfIdentifierQuads::new_from_identifier_list(&[1, 2, 3, 10, 4, 5, 6, 10]);
+    forest.set_max_living_trees(2);
+
+    forest.ensure_has_index_for(true, false, false, false);
+    assert_eq!(forest.built_index_orders()[4], 0);
+
+    // The maximum is reached, so the query uses the living trees
+    assert_eq!(forest.match_count(None, Some(5), None, None), 1);
+    assert_eq!(forest.get_number_of_living_trees(), 2);
+    assert_eq!(forest.built_index_orders()[4], 0);
+
+    forest.ensure_has_index_for(false, true, false, false);
+    assert_eq!(forest.get_number_of_living_trees(), 2);
+    assert_eq!(forest.built_index_orders()[4], 1);
+}
+
+#[wasm_bindgen_test]
+fn index_query_counts_are_aligned_with_orders() {
+    let forest = ForestOfIdentifierQuads::new_from_identifier_list(&[1, 2, 3, 10, 4, 5, 6, 10]);
+
+    assert_eq!(&*forest.built_index_orders(), &[2, 3, 1, 0]);
+    assert_eq!(&*forest.index_query_counts(), &[0]);
+
+    forest.match_count(None, None, Some(3), None);
+    forest.match_count(None, None, Some(6), Some(10));
+    assert_eq!(&*forest.index_query_counts(), &[2]);
+
+    forest.match_count(Some(1), None, None, None);
+    forest.match_count(Some(4), None, None, None);
+    let counts = forest.index_query_counts();
+    assert_eq!(forest.built_index_orders().len(), counts.len() * 4);
+    assert_eq!(counts.iter().sum::<u32>(), 4);
+}
+
+#[wasm_bindgen_test]
+fn index_query_counts_include_the_modifications() {
+    let mut forest = ForestOfIdentifierQuads::new_from_identifier_list(&[1, 2, 3, 10, 4, 5, 6, 10, 7, 8, 9, 20]);
+
+    forest.delete_matches(None, None, Some(3), None);
+    assert_eq!(&*forest.index_query_counts(), &[1]);
+
+    forest.retain_matching_any(&[-1, -1, 6, -1, -1, -1, -1, 30]).unwrap();
+    assert_eq!(&*forest.index_query_counts(), &[4]);
+    assert_eq!(forest.size(), 1);
+}
+
+#[wasm_bindgen_test]
+fn count_grouped_by_counts_the_objects() {
+    let forest = ForestOfIdentifierQuads::new_from_identifier_list(&[
+        1, 2, 3, 10, 1, 2, 3, 20, 1, 2, 4, 10, 1, 5, 3, 10, 6, 2, 3, 10
+    ]);
+
+    assert_eq!(&*forest.count_grouped_by(Some(1), Some(2), None, None, 2).unwrap(), &[3, 2, 4, 1]);
+    assert_eq!(&*forest.count_grouped_by(Some(1), Some(2), None, Some(20), 2).unwrap(), &[3, 1]);
+    assert_eq!(&*forest.count_grouped_by(None, None, Some(3), None, 0).unwrap(), &[1, 3, 6, 1]);
+    assert!(forest.count_grouped_by(Some(1), Some(2), Some(3), None, 2).is_err());
+    assert!(forest.count_grouped_by(None, None, None, None, 4).is_err());
+}
+
+#[wasm_bindgen_test]
+fn group_by_subject_round_trips() {
+    let identifier_list = [
+        1, 2, 3, 10,
+        4, 5, 6, 10,
+        1, 5, 3, 20,
+        1, 2, 3, 20
+    ];
+    let forest = ForestOfIdentifierQuads::new_from_identifier_list(&identifier_list);
+
+    let records = forest.group_by_subject();
+    assert_eq!(&records[0..2], &[1, 3]);
+
+    let mut quads = vec![];
+    let mut i = 0;
+    while i < records.len() {
+        let subject = records[i];
+        let number_of_quads = records[i + 1] as usize;
+        for pog in records[i + 2..i + 2 + number_of_quads * 3].chunks(3) {
+            quads.extend_from_slice(&[subject, pog[0], pog[1], pog[2]]);
+        }
+        i += 2 + number_of_quads * 3;
+    }
+
+    assert_eq!(sorted_quads(&quads), sorted_quads(&forest.get_all(None, None, None, None)));
+}
+
+#[wasm_bindgen_test]
+fn describe_node_returns_each_quad_once() {
+    let forest = ForestOfIdentifierQuads::new_from_identifier_list(&[
+        1, 2, 3, 10,
+        4, 1, 6, 10,
+        1, 5, 1, 1,
+        7, 8, 9, 10
+    ]);
+
+    assert_eq!(
+        sorted_quads(&forest.describe_node(1)),
+        vec![[1, 2, 3, 10], [1, 5, 1, 1], [4, 1, 6, 10]]
+    );
+    assert!(forest.describe_node(42).is_empty());
+}
+
+#[wasm_bindgen_test]
+fn cbd_follows_blank_nodes() {
+    // Identifiers from 100 are blank nodes
+    let forest = ForestOfIdentifierQuads::new_from_identifier_list(&[
+        1, 2, 100, 10,
+        100, 2, 101, 10,
+        101, 2, 100, 10,
+        101, 3, 4, 10,
+        1, 3, 4, 10,
+        4, 2, 5, 10,
+        6, 2, 7, 10
+    ]);
+
+    let description = forest.cbd(1, 100);
+    assert_eq!(
+        sorted_quads(&description.get_all(None, None, None, None)),
+        vec![[1, 2, 100, 10], [1, 3, 4, 10], [100, 2, 101, 10], [101, 2, 100, 10], [101, 3, 4, 10]]
+    );
+}
+
+#[wasm_bindgen_test]
+fn degrees_count_edges() {
+    let forest = ForestOfIdentifierQuads::new_from_identifier_list(&[
+        1, 2, 3, 10,
+        1, 2, 4, 10,
+        1, 5, 3, 20,
+        3, 2, 1, 10
+    ]);
+
+    assert_eq!(forest.out_degree(1, None), 3);
+    assert_eq!(forest.out_degree(1, Some(10)), 2);
+    assert_eq!(forest.in_degree(3, None), 2);
+    assert_eq!(forest.in_degree(3, Some(20)), 1);
+    assert_eq!(forest.in_degree(2, None), 0);
+}
+
+#[wasm_bindgen_test]
+fn project_to_default_graph_merges_triples() {
+    let forest = ForestOfIdentifierQuads::new_from_identifier_list(&[
+        1, 2, 3, 10,
+        1, 2, 3, 20,
+        4, 5, 6, 20
+    ]);
+
+    let projection = forest.project_to_default_graph(0);
+    assert_eq!(projection.size(), 2);
+    assert_eq!(
+        sorted_quads(&projection.get_all(None, None, None, None)),
+        vec![[1, 2, 3, 0], [4, 5, 6, 0]]
+    );
+}
+
+#[wasm_bindgen_test]
+fn has_triple_any_graph_ignores_the_graph() {
+    let forest = ForestOfIdentifierQuads::new_from_identifier_list(&[1, 2, 3, 10, 4, 5, 6, 20]);
+
+    assert!(forest.has_triple_any_graph(1, 2, 3));
+    assert!(forest.has_triple_any_graph(4, 5, 6));
+    assert!(!forest.has_triple_any_graph(1, 2, 6));
+}
+
+#[wasm_bindgen_test]
+fn jaccard_of_datasets() {
+    let lhs = ForestOfIdentifierQuads::new_from_identifier_list(&[1, 2, 3, 10, 4, 5, 6, 10, 7, 8, 9, 10]);
+    let rhs = ForestOfIdentifierQuads::new_from_identifier_list(&[1, 2, 3, 10, 4, 5, 6, 20]);
+
+    assert_eq!(lhs.jaccard(&rhs), 0.25);
+    assert_eq!(lhs.jaccard(&lhs), 1.0);
+    assert_eq!(ForestOfIdentifierQuads::new().jaccard(&ForestOfIdentifierQuads::new()), 1.0);
+}
+
+#[wasm_bindgen_test]
+fn set_operation_sizes() {
+    let lhs = ForestOfIdentifierQuads::new_from_identifier_list(&[1, 2, 3, 10, 4, 5, 6, 10, 7, 8, 9, 10]);
+    let rhs = ForestOfIdentifierQuads::new_from_identifier_list(&[1, 2, 3, 10, 4, 5, 6, 20]);
+
+    assert_eq!(lhs.intersection_size(&rhs), lhs.insersect(&rhs).size());
+    assert_eq!(lhs.union_size(&rhs), lhs.union(&rhs).size());
+    assert_eq!(lhs.difference_size(&rhs), lhs.difference(&rhs).size());
+    assert_eq!(rhs.difference_size(&lhs), rhs.difference(&lhs).size());
+
+    assert_eq!(lhs.intersection_size(&rhs), 1);
+    assert_eq!(lhs.union_size(&rhs), 4);
+    assert_eq!(lhs.difference_size(&rhs), 2);
+}
+
+#[wasm_bindgen_test]
+fn rollback_reverts_the_transaction() {
+    let identifier_list = [1, 2, 3, 10, 4, 5, 6, 10, 4, 5, 6, 20];
+    let mut forest = ForestOfIdentifierQuads::new_from_identifier_list(&identifier_list);
+
+    assert!(forest.commit().is_err());
+    assert!(forest.rollback().is_err());
+
+    forest.begin_transaction().unwrap();
+    assert!(forest.begin_transaction().is_err());
+    forest.add(7, 8, 9, 10);
+    forest.add(1, 2, 3, 10);
+    forest.remove(1, 2, 3, 10);
+    forest.add(1, 2, 3, 10);
+    forest.remove(4, 5, 6, 10);
+    forest.delete_matches(None, None, None, Some(20));
+    forest.rollback().unwrap();
+
+    assert_eq!(sorted_quads(&forest.get_all(None, None, None, None)), sorted_quads(&identifier_list));
+
+    forest.begin_transaction().unwrap();
+    forest.add(7, 8, 9, 10);
+    forest.commit().unwrap();
+    assert_eq!(forest.size(), 4);
+}
+
+#[wasm_bindgen_test]
+fn rollback_restores_the_journal_and_the_sequence() {
+    let mut forest = ForestOfIdentifierQuads::new();
+    forest.enable_journal();
+    forest.enable_insertion_sequence();
+    forest.add(1, 2, 3, 0);
+    forest.add(4, 5, 6, 0);
+
+    let journal = forest.export_journal();
+    let watermark = forest.current_watermark();
+
+    forest.begin_transaction().unwrap();
+    forest.remove(1, 2, 3, 0);
+    forest.set_strict_sentinel(Some(1));
+    forest.add(7, 8, 9, 0);
+    forest.rollback().unwrap();
+
+    assert_eq!(sorted_quads(&forest.get_all(None, None, None, None)), vec![[1, 2, 3, 0], [4, 5, 6, 0]]);
+    assert_eq!(forest.export_journal(), journal);
+    assert_eq!(forest.current_watermark(), watermark);
+    assert_eq!(&*forest.quads_since(0), &[1, 2, 3, 0, 4, 5, 6, 0]);
+}
+
+#[wasm_bindgen_test]
+fn rollback_after_clearing_the_journal_records_the_reverted_modifications() {
+    let mut forest = ForestOfIdentifierQuads::new_from_identifier_list(&[1, 2, 3, 0]);
+    let mut replica = ForestOfIdentifierQuads::new_from_identifier_list(&[1, 2, 3, 0]);
+    forest.enable_journal();
+
+    forest.begin_transaction().unwrap();
+    forest.remove(1, 2, 3, 0);
+    forest.add(4, 5, 6, 0);
+    replica.apply_journal(&forest.export_journal()).unwrap();
+    forest.clear_journal();
+    forest.add(7, 8, 9, 0);
+    forest.rollback().unwrap();
+
+    assert_eq!(&*forest.export_journal(), &[0, 7, 8, 9, 0, 1, 7, 8, 9, 0, 1, 4, 5, 6, 0, 0, 1, 2, 3, 0]);
+
+    replica.apply_journal(&forest.export_journal()).unwrap();
+    assert!(replica.has_same_elements(&forest));
+    assert_eq!(sorted_quads(&forest.get_all(None, None, None, None)), vec![[1, 2, 3, 0]]);
+}
+
+#[wasm_bindgen_test]
+fn journal_can_be_replayed() {
+    let mut source = ForestOfIdentifierQuads::new_from_identifier_list(&[1, 2, 3, 10]);
+    let mut replica = ForestOfIdentifierQuads::new_from_identifier_list(&[1, 2, 3, 10]);
+
+    assert!(source.export_journal().is_empty());
+    source.enable_journal();
+    source.add(4, 5, 6, 10);
+    source.add(4, 5, 6, 10);
+    source.remove(1, 2, 3, 10);
+    source.remove(7, 8, 9, 10);
+
+    let journal = source.export_journal();
+    assert_eq!(&*journal, &[0, 4, 5, 6, 10, 1, 1, 2, 3, 10]);
+
+    replica.apply_journal(&journal).unwrap();
+    assert!(replica.has_same_elements(&source));
+
+    source.clear_journal();
+    assert!(source.export_journal().is_empty());
+
+    assert!(replica.apply_journal(&[0, 1, 2, 3]).is_err());
+    assert!(replica.apply_journal(&[2, 1, 2, 3, 4]).is_err());
+}
+
+#[wasm_bindgen_test]
+fn net_change_since_snapshot() {
+    let mut forest = ForestOfIdentifierQuads::new_from_identifier_list(&[1, 2, 3, 10, 4, 5, 6, 10]);
+    assert_eq!(forest.net_change_since_snapshot(), 2);
+
+    forest.mark_snapshot();
+    forest.remove(1, 2, 3, 10);
+    forest.remove(4, 5, 6, 10);
+    forest.add(7, 8, 9, 10);
+    assert_eq!(forest.net_change_since_snapshot(), -1);
+}
+
+#[wasm_bindgen_test]
+fn match_bindings_returns_the_variables() {
+    let forest = ForestOfIdentifierQuads::new_from_identifier_list(&[
+        1, 2, 3, 10,
+        1, 2, 4, 20,
+        1, 5, 6, 10,
+        7, 2, 8, 10
+    ]);
+
+    let rows = forest.match_bindings(1, 2, -1, -1, false, 0).unwrap();
+    let mut rows = rows.chunks(2).map(|row| row.to_vec()).collect::<Vec<Vec<u32>>>();
+    rows.sort();
+    assert_eq!(rows, vec![vec![3, 10], vec![4, 20]]);
+
+    assert_eq!(forest.match_bindings(1, 5, 6, 10, false, 0).unwrap().len(), 0);
+    assert_eq!(forest.match_bindings(-1, -1, -1, -1, false, 0).unwrap().len(), 16);
+
+    assert!(forest.match_bindings(1, -2, -1, -1, false, 0).is_err());
+    assert!(forest.count_matching_each(&[-1, -1, -1, -1, 1, -1, -3, -1]).is_err());
+    assert!(forest.join_two_patterns(&[-1, -2, -1, -1], 0, &[-1, 2, -1, -1], 0, false, 0).is_err());
+}
+
+/// Groups a flattened list of rows of the given arity, sorted
+fn sorted_rows(flat_rows: &[u32], arity: usize) -> Vec<Vec<u32>> {
+    let mut rows = flat_rows.chunks(arity).map(|row| row.to_vec()).collect::<Vec<Vec<u32>>>();
+    rows.sort();
+    rows
+}
+
+#[wasm_bindgen_test]
+fn join_two_patterns_on_a_shared_variable() {
+    // 1 knows 2 and 3, 2 knows 4, 2 and 3 are named 5 and 6
+    let forest = ForestOfIdentifierQuads::new_from_identifier_list(&[
+        1, 100, 2, 0,
+        1, 100, 3, 0,
+        2, 100, 4, 0,
+        2, 101, 5, 0,
+        3, 101, 6, 0
+    ]);
+
+    // ?x knows ?y . ?y name ?n
+    let rows = forest.join_two_patterns(&[-1, 100, -1, 0], 2, &[-1, 101, -1, 0], 0, false, 0).unwrap();
+    assert_eq!(sorted_rows(&rows, 3), vec![vec![1, 2, 5], vec![1, 3, 6]]);
+
+    // Same join with the patterns swapped
+    let rows = forest.join_two_patterns(&[-1, 101, -1, 0], 0, &[-1, 100, -1, 0], 2, false, 0).unwrap();
+    assert_eq!(sorted_rows(&rows, 3), vec![vec![2, 5, 1], vec![3, 6, 1]]);
+
+    assert!(forest.join_two_patterns(&[-1, 100, -1, 0], 1, &[-1, 101, -1, 0], 0, false, 0).is_err());
+    assert!(forest.join_two_patterns(&[-1, 100, -1], 0, &[-1, 101, -1, 0], 0, false, 0).is_err());
+}
+
+#[wasm_bindgen_test]
+fn bindings_can_be_distinct_and_limited() {
+    let forest = ForestOfIdentifierQuads::new_from_identifier_list(&[
+        1, 100, 2, 0,
+        1, 100, 3, 0,
+        1, 100, 3, 10,
+        2, 100, 3, 0
+    ]);
+
+    assert_eq!(sorted_rows(&forest.match_bindings(-1, 100, -1, -1, false, 0).unwrap(), 2).len(), 4);
+    assert_eq!(sorted_rows(&forest.match_bindings(-1, 100, -1, -1, true, 0).unwrap(), 2), vec![vec![1, 2], vec![1, 3], vec![2, 3]]);
+    assert_eq!(forest.match_bindings(-1, 100, -1, -1, true, 2).unwrap().len(), 2 * 2);
+    assert_eq!(forest.match_bindings(-1, 100, -1, -1, false, 10).unwrap().len(), 4 * 2);
+
+    // ?x p ?y . ?y p ?z
+    let rows = forest.join_two_patterns(&[-1, 100, -1, -1], 2, &[-1, 100, -1, -1], 0, false, 0).unwrap();
+    assert_eq!(sorted_rows(&rows, 5), vec![vec![1, 2, 0, 3, 0]]);
+
+    let rows = forest.join_two_patterns(&[-1, 100, -1, 0], 2, &[-1, 100, -1, 0], 0, true, 1).unwrap();
+    assert_eq!(&*rows, &[1, 2, 3]);
+}
+
+#[wasm_bindgen_test]
+fn star_join_groups_objects_by_predicate() {
+    let forest = ForestOfIdentifierQuads::new_from_identifier_list(&[
+        1, 100, 2, 0,
+        1, 100, 3, 0,
+        1, 101, 4, 10,
+        1, 102, 5, 0,
+        6, 100, 7, 0
+    ]);
+
+    let mut result = forest.star_join(1, &[101, 100, 103], None).to_vec();
+    result[5..7].sort();
+    assert_eq!(result, vec![101, 1, 4, 100, 2, 2, 3, 103, 0]);
+
+    assert_eq!(&*forest.star_join(1, &[101], Some(0)), &[101, 0]);
+}
+
+#[wasm_bindgen_test]
+fn materialize_inverse_is_idempotent() {
+    let mut forest = ForestOfIdentifierQuads::new_from_identifier_list(&[
+        1, 100, 2, 0,
+        3, 100, 4, 10,
+        2, 101, 1, 0
+    ]);
+
+    forest.materialize_inverse(100, 101);
+    let expected = vec![[1, 100, 2, 0], [2, 101, 1, 0], [3, 100, 4, 10], [4, 101, 3, 10]];
+    assert_eq!(sorted_quads(&forest.get_all(None, None, None, None)), expected);
+
+    forest.materialize_inverse(100, 101);
+    assert_eq!(sorted_quads(&forest.get_all(None, None, None, None)), expected);
+}
+
+#[wasm_bindgen_test]
+fn symmetric_closure_counts_added_quads() {
+    let mut forest = ForestOfIdentifierQuads::new_from_identifier_list(&[
+        1, 100, 2, 0,
+        2, 100, 1, 0,
+        3, 100, 4, 10,
+        5, 100, 5, 0,
+        6, 101, 7, 0
+    ]);
+
+    assert_eq!(forest.symmetric_closure(100), 1);
+    assert!(forest.has(4, 100, 3, 10));
+    assert_eq!(forest.symmetric_closure(100), 0);
+    assert_eq!(forest.size(), 6);
+}
+
+#[wasm_bindgen_test]
+fn to_nquads_omits_the_default_graph() {
+    let forest = ForestOfIdentifierQuads::new_from_identifier_list(&[1, 2, 3, 0, 1, 2, 1, 4]);
+    let resolver = js_sys::Function::new_with_args("id", "return '<http://example.org/' + id + '>';");
+
+    let nquads = forest.to_nquads(&resolver).unwrap();
+    let mut lines = nquads.lines().collect::<Vec<&str>>();
+    lines.sort();
+    assert_eq!(lines, vec![
+        "<http://example.org/1> <http://example.org/2> <http://example.org/1> <http://example.org/4> .",
+        "<http://example.org/1> <http://example.org/2> <http://example.org/3> ."
+    ]);
+
+    let throwing_resolver = js_sys::Function::new_no_args("throw new Error('unknown term');");
+    assert!(forest.to_nquads(&throwing_resolver).is_err());
+}
+
+#[wasm_bindgen_test]
+fn canonical_order_does_not_depend_on_insertion_order() {
+    let mut lhs = ForestOfIdentifierQuads::new();
+    let mut rhs = ForestOfIdentifierQuads::new();
+
+    let quads = [[4, 5, 6, 10], [1, 2, 3, 20], [1, 2, 3, 10], [1, 1, 9, 10]];
+    for quad in quads.iter() {
+        lhs.add(quad[0], quad[1], quad[2], quad[3]);
+    }
+    for quad in quads.iter().rev() {
+        rhs.add(quad[0], quad[1], quad[2], quad[3]);
+    }
+    rhs.ensure_has_index_for(true, false, false, false);
+
+    assert_eq!(lhs.get_all_canonical(), rhs.get_all_canonical());
+    assert_eq!(&*lhs.get_all_canonical(), &[1, 1, 9, 10, 1, 2, 3, 10, 1, 2, 3, 20, 4, 5, 6, 10]);
+}
+
+#[wasm_bindgen_test]
+fn base_order_is_stable() {
+    let mut forest = ForestOfIdentifierQuads::new_from_identifier_list(&[
+        4, 5, 6, 10,
+        1, 2, 3, 20,
+        9, 2, 3, 10,
+        1, 8, 6, 10
+    ]);
+
+    let ogps = [9, 2, 3, 10, 1, 2, 3, 20, 4, 5, 6, 10, 1, 8, 6, 10];
+    assert_eq!(&*forest.get_all_in_base_order(), &ogps);
+
+    forest.ensure_has_index_for(true, false, false, false);
+    forest.ensure_has_index_for(false, true, false, false);
+    forest.match_count(None, None, None, Some(10));
+    assert_eq!(&*forest.get_all_in_base_order(), &ogps);
+}
+
+#[wasm_bindgen_test]
+fn trees_are_consistent() {
+    let mut forest = ForestOfIdentifierQuads::new_from_identifier_list(&[
+        1, 2, 3, 10,
+        4, 5, 6, 10,
+        1, 5, 3, 20
+    ]);
+    assert!(forest.check_consistency());
+
+    forest.ensure_has_index_for(true, false, false, false);
+    forest.ensure_has_index_for(false, true, false, false);
+    forest.remove(4, 5, 6, 10);
+    forest.add(7, 8, 9, 10);
+    assert!(forest.check_consistency());
+
+    forest.delete_matches(None, None, None, Some(10));
+    forest.ensure_has_index_for(false, false, false, true);
+    assert!(forest.check_consistency());
+}
+
+#[wasm_bindgen_test]
+fn to_quad_array_returns_one_array_per_quad() {
+    let forest = ForestOfIdentifierQuads::new_from_identifier_list(&[1, 2, 3, 10, 4, 5, 6, 10, 1, 5, 3, 20]);
+
+    let array = forest.to_quad_array(Some(1), None, None, None);
+    assert_eq!(array.length(), 2);
+
+    let mut quads = array
+        .iter()
+        .map(|quad| js_sys::Uint32Array::from(quad).to_vec())
+        .collect::<Vec<Vec<u32>>>();
+    quads.sort();
+    assert_eq!(quads, vec![vec![1, 2, 3, 10], vec![1, 5, 3, 20]]);
+}
+
+#[wasm_bindgen_test]
+fn match_pattern_is_new_from() {
+    let forest = ForestOfIdentifierQuads::new_from_identifier_list(&[1, 2, 3, 10, 4, 5, 6, 10, 1, 5, 3, 20]);
+
+    let matched = forest.match_pattern(None, Some(5), None, None);
+    assert!(matched.has_same_elements(&forest.new_from(None, Some(5), None, None)));
+    assert_eq!(matched.size(), 2);
+}
+
+#[wasm_bindgen_test]
+fn quads_can_be_passed_as_slices() {
+    let mut forest = ForestOfIdentifierQuads::new();
+
+    forest.add_quad(&[1, 2, 3, 10]);
+    assert!(forest.has_quad(&[1, 2, 3, 10]));
+    assert!(!forest.has_quad(&[1, 2, 3, 20]));
+
+    forest.remove_quad(&[1, 2, 3, 10]);
+    assert_eq!(forest.size(), 0);
+}
+
+#[wasm_bindgen_test]
+fn count_matching_each_pattern() {
+    let forest = ForestOfIdentifierQuads::new_from_identifier_list(&[1, 2, 3, 10, 4, 5, 6, 10, 1, 5, 3, 20]);
+
+    assert_eq!(
+        &*forest.count_matching_each(&[-1, -1, -1, -1, 1, -1, -1, -1, -1, 5, -1, 10, 7, -1, -1, -1]).unwrap(),
+        &[3, 2, 1, 0]
+    );
+    assert!(forest.count_matching_each(&[]).unwrap().is_empty());
+}
+
+#[wasm_bindgen_test]
+fn frozen_datasets_answer_queries() {
+    let identifier_list = [
+        1, 2, 3, 10,
+        1, 2, 4, 10,
+        1, 5, 3, 20,
+        4, 5, 6, 10
+    ];
+    let mut forest = ForestOfIdentifierQuads::new_from_identifier_list(&identifier_list);
+    forest.ensure_has_index_for(false, true, false, false);
+
+    forest.freeze();
+    assert!(forest.is_frozen());
+    assert_eq!(forest.get_number_of_living_trees(), 1);
+
+    assert_eq!(sorted_quads(&forest.get_all(None, None, None, None)), sorted_quads(&identifier_list));
+    assert_eq!(sorted_quads(&forest.get_all(Some(1), Some(2), None, None)), vec![[1, 2, 3, 10], [1, 2, 4, 10]]);
+    assert_eq!(sorted_quads(&forest.get_all(None, Some(5), None, None)), vec![[1, 5, 3, 20], [4, 5, 6, 10]]);
+    assert_eq!(forest.match_count(Some(1), None, Some(3), None), 2);
+    assert_eq!(forest.match_count(Some(7), None, None, None), 0);
+
+    forest.add(1, 2, 3, 10);
+    assert!(forest.is_frozen());
+
+    forest.add(7, 8, 9, 10);
+    assert!(!forest.is_frozen());
+    assert_eq!(forest.match_count(Some(7), None, None, None), 1);
+}
+
+#[wasm_bindgen_test]
+fn membership_index_is_kept_up_to_date() {
+    let mut forest = ForestOfIdentifierQuads::new_from_identifier_list(&[1, 2, 3, 10, 4, 5, 6, 10, 1, 5, 3, 20]);
+    forest.build_membership_index();
+
+    assert!(forest.has(1, 2, 3, 10));
+    forest.remove(1, 2, 3, 10);
+    assert!(!forest.has(1, 2, 3, 10));
+    forest.add(7, 8, 9, 10);
+    assert!(forest.has(7, 8, 9, 10));
+    forest.delete_matches(None, None, None, Some(10));
+    assert!(!forest.has(4, 5, 6, 10));
+    assert!(!forest.has(7, 8, 9, 10));
+
+    assert!(forest.contains_slice(&[1, 5, 3, 20]));
+    assert_eq!(forest.intersect_slice(&[1, 5, 3, 20, 4, 5, 6, 10]).size(), 1);
+}
+
+#[wasm_bindgen_test]
+fn extend_from_slice_counts_new_quads() {
+    let mut forest = ForestOfIdentifierQuads::new_from_identifier_list(&[1, 2, 3, 10]);
+
+    assert_eq!(forest.extend_from_slice(&[1, 2, 3, 10, 4, 5, 6, 10, 4, 5, 6, 10, 7, 8, 9, 10]), 2);
+    assert_eq!(forest.size(), 3);
+    assert_eq!(forest.extend_from_slice(&[]), 0);
+}
+
+#[wasm_bindgen_test]
+fn distinct_triples_ignore_graphs() {
+    let forest = ForestOfIdentifierQuads::new_from_identifier_list(&[
+        1, 2, 3, 10, 1, 2, 3, 20, 1, 2, 3, 30, 4, 5, 6, 10
+    ]);
+
+    assert_eq!(&*forest.distinct_triples(), &[1, 2, 3, 4, 5, 6]);
+}
+
+#[wasm_bindgen_test]
+fn reflexive_quads_have_the_same_subject_and_object() {
+    let forest = ForestOfIdentifierQuads::new_from_identifier_list(&[
+        1, 2, 1, 10, 1, 2, 3, 10, 4, 5, 4, 20
+    ]);
+
+    assert_eq!(sorted_quads(&forest.reflexive_quads(None)), vec![[1, 2, 1, 10], [4, 5, 4, 20]]);
+    assert_eq!(sorted_quads(&forest.reflexive_quads(Some(20))), vec![[4, 5, 4, 20]]);
+}
+
+#[wasm_bindgen_test]
+fn top_predicates_are_the_most_frequent() {
+    let forest = ForestOfIdentifierQuads::new_from_identifier_list(&[
+        1, 2, 3, 10, 4, 2, 6, 10, 7, 2, 9, 10,
+        1, 5, 3, 10, 4, 5, 6, 10,
+        1, 8, 3, 10, 4, 8, 6, 10,
+        1, 11, 3, 10
+    ]);
+
+    assert_eq!(&*forest.top_predicates(2), &[2, 3, 5, 2]);
+    assert!(forest.top_predicates(0).is_empty());
+    assert_eq!(forest.top_predicates(10).len(), 8);
+}
+
+#[wasm_bindgen_test]
+fn id_bounds_cover_every_position() {
+    let forest = ForestOfIdentifierQuads::new_from_identifier_list(&[4, 5, 6, 10, 7, 8, 9, 2]);
+    assert_eq!(&*forest.id_bounds(), &[2, 10]);
+
+    assert!(ForestOfIdentifierQuads::new().id_bounds().is_empty());
+}
+
+#[wasm_bindgen_test]
+fn compacted_ids_are_dense() {
+    let identifier_list = [1, 20, 300, 0, 300, 20, 1, 4000, 300, 20, 300, 0];
+    let forest = ForestOfIdentifierQuads::new_from_identifier_list(&identifier_list);
+
+    let result = forest.compact_ids();
+    let mapping = result.mapping();
+    assert_eq!(&*mapping, &[0, 1, 20, 300, 4000]);
+
+    let compacted = result.into_dataset();
+    assert_eq!(compacted.size(), 3);
+    assert!(compacted.has(1, 2, 3, 0));
+    assert!(compacted.has(3, 2, 1, 4));
+    assert!(compacted.has(3, 2, 3, 0));
+
+    let original_ids = compacted.get_all(None, None, None, None)
+        .iter()
+        .map(|id| mapping[*id as usize])
+        .collect::<Vec<u32>>();
+    assert_eq!(sorted_quads(&original_ids), sorted_quads(&identifier_list));
+}
+
+#[wasm_bindgen_test]
+fn compact_ids_keeps_an_unused_default_graph() {
+    let forest = ForestOfIdentifierQuads::new_from_identifier_list(&[5, 6, 7, 8]);
+
+    let result = forest.compact_ids();
+    assert_eq!(&*result.mapping(), &[0, 5, 6, 7, 8]);
+
+    let compacted = result.into_dataset();
+    assert_eq!(compacted.default_graph_id(), 0);
+    assert!(compacted.has(1, 2, 3, 4));
+    assert_eq!(compacted.get_default_graph().len(), 0);
+}
+
+#[wasm_bindgen_test]
+fn compact_ids_does_not_move_another_graph_into_the_default_graph() {
+    let mut forest = ForestOfIdentifierQuads::with_default_graph(9);
+    forest.add(5, 6, 7, 8);
+    forest.add(5, 6, 7, 10);
+
+    let result = forest.compact_ids();
+    assert_eq!(&*result.mapping(), &[5, 6, 7, 8, 9, 10]);
+
+    let compacted = result.into_dataset();
+    assert_eq!(compacted.default_graph_id(), 4);
+    assert_eq!(compacted.get_default_graph().len(), 0);
+    assert_eq!(compacted.count_graphs(false), 2);
+}
+
+#[wasm_bindgen_test]
+fn union_into_keeps_the_built_trees() {
+    let mut forest = ForestOfIdentifierQuads::new_from_identifier_list(&[1, 2, 3, 10, 4, 5, 6, 10]);
+    forest.ensure_has_index_for(true, false, false, false);
+    assert_eq!(forest.get_number_of_living_trees(), 2);
+
+    let other = ForestOfIdentifierQuads::new_from_identifier_list(&[1, 2, 3, 10, 1, 8, 9, 20]);
+    assert_eq!(forest.union_into(&other), 1);
+
+    assert_eq!(forest.get_number_of_living_trees(), 2);
+    assert_eq!(forest.size(), 3);
+    assert_eq!(sorted_quads(&forest.get_all(Some(1), None, None, None)), vec![[1, 2, 3, 10], [1, 8, 9, 20]]);
+    assert_eq!(other.size(), 2);
+}
+
+#[wasm_bindgen_test]
+fn import_graph_from_only_adds_the_graph() {
+    let mut forest = ForestOfIdentifierQuads::new_from_identifier_list(&[1, 2, 3, 10]);
+    let other = ForestOfIdentifierQuads::new_from_identifier_list(&[
+        1, 2, 3, 10, 4, 5, 6, 10, 7, 8, 9, 20
+    ]);
+
+    assert_eq!(forest.import_graph_from(&other, 10), 1);
+    assert_eq!(forest.import_graph_from(&other, 30), 0);
+
+    assert_eq!(sorted_quads(&forest.get_all(None, None, None, None)), vec![[1, 2, 3, 10], [4, 5, 6, 10]]);
+    assert_eq!(other.size(), 3);
+}
+
+#[wasm_bindgen_test]
+fn default_graph_can_be_chosen() {
+    assert_eq!(ForestOfIdentifierQuads::new().default_graph_id(), 0);
+
+    let mut forest = ForestOfIdentifierQuads::with_default_graph(9);
+    forest.add(1, 2, 3, 9);
+    forest.add(1, 2, 3, 0);
+    assert_eq!(forest.default_graph_id(), 9);
+    assert_eq!(forest.new_from(Some(1), None, None, None).default_graph_id(), 9);
+
+    let resolver = js_sys::Function::new_with_args("id", "return '<' + id + '>';");
+    let nquads = forest.to_nquads(&resolver).unwrap();
+    let mut lines = nquads.lines().collect::<Vec<&str>>();
+    lines.sort();
+    assert_eq!(lines, vec!["<1> <2> <3> .", "<1> <2> <3> <0> ."]);
+}
+
+#[wasm_bindgen_test]
+fn graphs_can_be_counted() {
+    let forest = ForestOfIdentifierQuads::new_from_identifier_list(&[
+        1, 2, 3, 0, 1, 2, 3, 10, 4, 5, 6, 10, 4, 5, 6, 20
+    ]);
+
+    assert_eq!(forest.count_graphs(true), 3);
+    assert_eq!(forest.count_graphs(false), 2);
+    assert!(forest.has_graph(20));
+    assert!(!forest.has_graph(30));
+}
+
+#[wasm_bindgen_test]
+fn default_graph_triples_exclude_named_graphs() {
+    let forest = ForestOfIdentifierQuads::new_from_identifier_list(&[
+        1, 2, 3, 0, 4, 5, 6, 10, 7, 8, 9, 0
+    ]);
+
+    assert_eq!(sorted_rows(&forest.get_default_graph(), 3), vec![vec![1, 2, 3], vec![7, 8, 9]]);
+
+    let mut forest = ForestOfIdentifierQuads::with_default_graph(10);
+    forest.add(1, 2, 3, 0);
+    forest.add(4, 5, 6, 10);
+    assert_eq!(&*forest.get_default_graph(), &[4, 5, 6]);
+}
+
+#[wasm_bindgen_test]
+fn two_hop_neighbors_follow_two_edges() {
+    // 1 -> 2 -> 3, 1 -> 4 -> 3, 4 -> 1, 2 -> 5 in another graph
+    let forest = ForestOfIdentifierQuads::new_from_identifier_list(&[
+        1, 100, 2, 0, 2, 100, 3, 0, 1, 101, 4, 0, 4, 100, 3, 0, 4, 100, 1, 0, 2, 100, 5, 10
+    ]);
+
+    assert_eq!(&*forest.two_hop_neighbors(1, None), &[3, 5]);
+    assert_eq!(&*forest.two_hop_neighbors(1, Some(0)), &[3]);
+    assert!(forest.two_hop_neighbors(3, None).is_empty());
+}
+
+#[wasm_bindgen_test]
+fn paths_are_found_through_cycles() {
+    // 1 -> 2 -> 3 -> 1 -> 4 with predicate 100, 4 -> 5 with predicate 101
+    let forest = ForestOfIdentifierQuads::new_from_identifier_list(&[
+        1, 100, 2, 0, 2, 100, 3, 0, 3, 100, 1, 0, 3, 100, 4, 0, 4, 101, 5, 0
+    ]);
+
+    assert!(forest.path_exists(1, 4, 100, None, None));
+    assert!(forest.path_exists(2, 4, 100, Some(0), None));
+    assert!(!forest.path_exists(1, 5, 100, None, None));
+    assert!(!forest.path_exists(4, 1, 100, None, None));
+    assert!(!forest.path_exists(1, 4, 100, Some(10), None));
+
+    assert!(forest.path_exists(1, 4, 100, None, Some(3)));
+    assert!(!forest.path_exists(1, 4, 100, None, Some(2)));
+}
+
+/// Calls the method `name` of the JS object `object`
+fn call_method(object: &JsValue, name: &str, arguments: &js_sys::Array) -> JsValue {
+    let method = js_sys::Function::from(js_sys::Reflect::get(object, &JsValue::from_str(name)).unwrap());
+    js_sys::Reflect::apply(&method, object, arguments).unwrap()
+}
+
+#[wasm_bindgen_test]
+fn split_by_graph_gives_one_dataset_per_graph() {
+    let identifier_list = [1, 2, 3, 20, 4, 5, 6, 10, 1, 2, 3, 10, 7, 8, 9, 0];
+    let forest = ForestOfIdentifierQuads::new_from_identifier_list(&identifier_list);
+
+    let parts = forest.split_by_graph(true).unwrap();
+    assert_eq!(parts.length(), 3);
+
+    let mut recombined = vec![];
+    for (part, expected_graph) in parts.iter().zip(&[0, 10, 20]) {
+        let graph = js_sys::Reflect::get(&part, &JsValue::from_str("graph")).unwrap();
+        assert_eq!(graph.as_f64(), Some(*expected_graph as f64));
+
+        let dataset = js_sys::Reflect::get(&part, &JsValue::from_str("dataset")).unwrap();
+        let quads = call_method(&dataset, "get_all", &js_sys::Array::new());
+        recombined.extend(js_sys::Uint32Array::new(&quads).to_vec());
+    }
+    assert_eq!(sorted_quads(&recombined), sorted_quads(&identifier_list));
+
+    let parts = forest.split_by_graph(false).unwrap();
+    let dataset = js_sys::Reflect::get(&parts.get(1), &JsValue::from_str("dataset")).unwrap();
+    let quads = call_method(&dataset, "get_all", &js_sys::Array::new());
+    assert_eq!(sorted_quads(&js_sys::Uint32Array::new(&quads).to_vec()), vec![[1, 2, 3, 0], [4, 5, 6, 0]]);
+}
+
+#[wasm_bindgen_test]
+fn partition_by_predicate_ends_with_the_other_predicates() {
+    let forest = ForestOfIdentifierQuads::new_from_identifier_list(&[
+        1, 2, 3, 10, 4, 2, 6, 10, 1, 5, 3, 10, 1, 8, 3, 10, 4, 9, 6, 20
+    ]);
+
+    let parts = forest.partition_by_predicate(&[2, 5]);
+    assert_eq!(parts.length(), 3);
+
+    let quads_of = |i: u32| {
+        let quads = call_method(&parts.get(i), "get_all", &js_sys::Array::new());
+        sorted_quads(&js_sys::Uint32Array::new(&quads).to_vec())
+    };
+    assert_eq!(quads_of(0), vec![[1, 2, 3, 10], [4, 2, 6, 10]]);
+    assert_eq!(quads_of(1), vec![[1, 5, 3, 10]]);
+    assert_eq!(quads_of(2), vec![[1, 8, 3, 10], [4, 9, 6, 20]]);
+}
+
+#[wasm_bindgen_test]
+fn json_round_trip() {
+    let identifier_list = [1, 2, 3, 10, 4, 5, 6, 0, 4294967295, 8, 9, 10];
+    let forest = ForestOfIdentifierQuads::new_from_identifier_list(&identifier_list);
+
+    let read_forest = ForestOfIdentifierQuads::from_json(&forest.to_json()).unwrap();
+    assert_eq!(sorted_quads(&read_forest.get_all(None, None, None, None)), sorted_quads(&identifier_list));
+
+    let read_forest = ForestOfIdentifierQuads::from_json(" [ [1, 2, 3, 4] ,[5,6,7,8]\n] ").unwrap();
+    assert_eq!(read_forest.size(), 2);
+    assert_eq!(ForestOfIdentifierQuads::from_json("[]").unwrap().size(), 0);
+
+    for malformed_json in &["", "[", "[[1,2,3]]", "[[1,2,3,-4]]", "[[1,2,3,4],]", "[[1,2,3,4]] x", "[[1,2,3,4294967296]]"] {
+        assert!(ForestOfIdentifierQuads::from_json(malformed_json).is_err());
+    }
+}
+
+#[wasm_bindgen_test]
+fn sorted_bytes_round_trip() {
+    let identifier_list = [1, 2, 3, 10, 4, 5, 6, 0, 4294967295, 8, 9, 10];
+    let forest = ForestOfIdentifierQuads::new_from_identifier_list(&identifier_list);
+    let bytes = forest.to_bytes_sorted();
+    assert_eq!(bytes.len(), 4 + 3 * 16);
+
+    for freeze in &[false, true] {
+        let read_forest = ForestOfIdentifierQuads::from_bytes_sorted(&bytes, *freeze).unwrap();
+        assert_eq!(read_forest.is_frozen(), *freeze);
+        assert_eq!(sorted_quads(&read_forest.get_all(None, None, None, None)), sorted_quads(&identifier_list));
+        assert_eq!(&*read_forest.to_bytes_sorted(), &*bytes);
+    }
+
+    assert!(ForestOfIdentifierQuads::from_bytes_sorted(&bytes[0..bytes.len() - 1], false).is_err());
+
+    let mut wrong_order = bytes.to_vec();
+    wrong_order.swap(0, 1);
+    assert!(ForestOfIdentifierQuads::from_bytes_sorted(&wrong_order, false).is_err());
+
+    let mut unsorted = bytes[0..4].to_vec();
+    unsorted.extend_from_slice(&bytes[20..36]);
+    unsorted.extend_from_slice(&bytes[4..20]);
+    assert!(ForestOfIdentifierQuads::from_bytes_sorted(&unsorted, false).is_err());
+}
+
+#[wasm_bindgen_test]
+fn iter_quads_returns_every_quad() {
+    let identifier_list = [1, 2, 3, 10, 4, 5, 6, 10, 7, 8, 9, 20];
+    let forest = ForestOfIdentifierQuads::new_from_identifier_list(&identifier_list);
+
+    let quads = forest.iter_quads().flatten().collect::<Vec<u32>>();
+    assert_eq!(&*quads, &*forest.get_all_in_base_order());
+}
+
+#[wasm_bindgen_test]
+fn range_prefix_length_depends_on_the_living_trees() {
+    let mut forest = ForestOfIdentifierQuads::new_from_identifier_list(&[1, 2, 3, 10]);
+
+    // Only the OGPS base tree is alive
+    assert_eq!(forest.range_prefix_length(false, false, false, false), 0);
+    assert_eq!(forest.range_prefix_length(false, false, true, false), 1);
+    assert_eq!(forest.range_prefix_length(true, false, true, false), 1);
+    assert_eq!(forest.range_prefix_length(false, true, true, true), 3);
+    assert_eq!(forest.range_prefix_length(true, false, false, false), 0);
+
+    forest.ensure_has_index_for(true, false, false, false);
+    assert_eq!(forest.range_prefix_length(true, false, false, false), 1);
+    assert_eq!(forest.range_prefix_length(true, true, false, false), 2);
+    assert_eq!(forest.range_prefix_length(true, false, true, false), 1);
+    assert_eq!(forest.range_prefix_length(false, true, true, true), 3);
+}
+
+#[wasm_bindgen_test]
+fn intersection_identifier_list_is_insersect() {
+    let forest = ForestOfIdentifierQuads::new_from_identifier_list(&[1, 2, 3, 10, 4, 5, 6, 10, 7, 8, 9, 20]);
+    let other = ForestOfIdentifierQuads::new_from_identifier_list(&[4, 5, 6, 10, 7, 8, 9, 20, 1, 2, 3, 20]);
+
+    let expected = sorted_quads(&forest.insersect(&other).get_all(None, None, None, None));
+    assert_eq!(sorted_quads(&forest.intersection_identifier_list(&other)), expected);
+    assert_eq!(sorted_quads(&other.intersection_identifier_list(&forest)), expected);
+    assert!(forest.intersection_identifier_list(&ForestOfIdentifierQuads::new()).is_empty());
+
+    // Sorted in OGPS order, whether the datasets are frozen or not
+    let mut frozen = ForestOfIdentifierQuads::new_from_identifier_list(&[7, 8, 9, 20, 4, 5, 6, 10, 1, 2, 3, 10]);
+    frozen.freeze();
+    assert_eq!(&*frozen.intersection_identifier_list(&other), &[4, 5, 6, 10, 7, 8, 9, 20]);
+    assert_eq!(&*other.intersection_identifier_list(&forest), &[4, 5, 6, 10, 7, 8, 9, 20]);
+}
+
+#[wasm_bindgen_test]
+fn add_if_absent_returns_the_quads_already_present() {
+    let mut forest = ForestOfIdentifierQuads::new_from_identifier_list(&[1, 2, 3, 10]);
+
+    let present = forest.add_if_absent(&[1, 2, 3, 10, 4, 5, 6, 10, 4, 5, 6, 10, 7, 8, 9, 10]);
+    assert_eq!(&*present, &[1, 2, 3, 10, 4, 5, 6, 10]);
+    assert_eq!(forest.size(), 3);
+}
+
+#[wasm_bindgen_test]
+fn missing_from_slice_returns_the_stale_quads() {
+    let forest = ForestOfIdentifierQuads::new_from_identifier_list(&[1, 2, 3, 10, 4, 5, 6, 10, 7, 8, 9, 20]);
+
+    let missing = forest.missing_from_slice(&[4, 5, 6, 10, 1, 1, 1, 1]);
+    assert_eq!(sorted_quads(&missing), vec![[1, 2, 3, 10], [7, 8, 9, 20]]);
+    assert!(forest.missing_from_slice(&forest.get_all(None, None, None, None)).is_empty());
+}
+
+#[wasm_bindgen_test]
+fn equality_can_ignore_graphs() {
+    let forest = ForestOfIdentifierQuads::new_from_identifier_list(&[1, 2, 3, 10, 1, 2, 3, 20, 4, 5, 6, 10]);
+    let other = ForestOfIdentifierQuads::new_from_identifier_list(&[4, 5, 6, 0, 1, 2, 3, 30]);
+
+    assert!(forest.equals_ignoring_graph(&other));
+    assert!(!forest.has_same_elements(&other));
+
+    let different = ForestOfIdentifierQuads::new_from_identifier_list(&[4, 5, 6, 0]);
+    assert!(!forest.equals_ignoring_graph(&different));
+}
+
+#[wasm_bindgen_test]
+fn bulk_load_counts_the_added_quads() {
+    let mut forest = ForestOfIdentifierQuads::new_from_identifier_list(&[1, 2, 3, 10]);
+    forest.ensure_has_index_for(true, false, false, false);
+
+    let mut loader = forest.begin_bulk_load();
+    assert_eq!(loader.push_chunk(&[1, 2, 3, 10, 4, 5, 6, 10]), 1);
+    assert_eq!(loader.push_chunk(&[7, 8, 9, 10, 1, 5, 6, 20]), 3);
+    assert_eq!(loader.push_chunk(&[]), 3);
+
+    let forest = loader.finish();
+    assert_eq!(forest.size(), 4);
+    assert_eq!(sorted_quads(&forest.get_all(Some(1), None, None, None)), vec![[1, 2, 3, 10], [1, 5, 6, 20]]);
+}
+
+#[wasm_bindgen_test]
+fn rebuild_from_base_recounts_the_statistics() {
+    let mut forest = ForestOfIdentifierQuads::new_from_identifier_list(&[1, 2, 3, 10, 4, 5, 6, 10]);
+    forest.enable_statistics();
+    assert_eq!(&*forest.distinct_term_counts(), &[2, 2, 2, 1]);
+
+    forest.rebuild_from_base();
+    assert_eq!(&*forest.distinct_term_counts(), &[2, 2, 2, 1]);
+
+    forest.add(7, 2, 3, 20);
+    assert_eq!(&*forest.distinct_term_counts(), &[3, 2, 2, 2]);
+}
+
+#[wasm_bindgen_test]
+fn rebuild_from_base_keeps_the_quads() {
+    let identifier_list = [1, 2, 3, 10, 4, 5, 6, 10, 7, 8, 9, 20];
+    let mut forest = ForestOfIdentifierQuads::new_from_identifier_list(&identifier_list);
+    forest.ensure_has_index_for(true, false, false, false);
+    forest.build_membership_index();
+
+    forest.rebuild_from_base();
+    assert_eq!(forest.get_number_of_living_trees(), 1);
+    assert_eq!(forest.size(), 3);
+    assert!(forest.check_consistency());
+    assert_eq!(sorted_quads(&forest.get_all(Some(4), None, None, None)), vec![[4, 5, 6, 10]]);
+    assert!(forest.has(7, 8, 9, 20));
+
+    forest.freeze();
+    forest.rebuild_from_base();
+    assert!(forest.is_frozen());
+    assert_eq!(sorted_quads(&forest.get_all(None, None, None, None)), sorted_quads(&identifier_list));
+}
+
+#[wasm_bindgen_test]
+fn slice_operations_do_not_depend_on_the_largest_side() {
+    let forest = ForestOfIdentifierQuads::new_from_identifier_list(&[1, 2, 3, 10, 4, 5, 6, 10]);
+
+    let large_slice = [1, 2, 3, 10, 7, 8, 9, 10, 7, 8, 9, 20, 4, 5, 6, 10, 1, 2, 3, 10];
+    assert_eq!(sorted_quads(&forest.intersect_slice(&large_slice).get_all(None, None, None, None)),
+        vec![[1, 2, 3, 10], [4, 5, 6, 10]]);
+    assert!(!forest.contains_slice(&large_slice));
+    assert!(forest.contains_slice(&[1, 2, 3, 10, 4, 5, 6, 10, 1, 2, 3, 10]));
+    assert!(!forest.contains_slice(&[1, 2, 3, 10, 1, 2, 3, 10, 7, 8, 9, 10]));
+
+    let small_slice = [1, 2, 3, 10];
+    assert_eq!(forest.intersect_slice(&small_slice).size(), 1);
+    assert!(forest.contains_slice(&small_slice));
+}
+
+#[wasm_bindgen_test]
+fn index_sizes_are_aligned_with_the_orders() {
+    let mut forest = ForestOfIdentifierQuads::new_from_identifier_list(&[1, 2, 3, 10, 4, 5, 6, 10]);
+    assert_eq!(&*forest.index_sizes(), &[2]);
+
+    forest.ensure_has_index_for(true, false, false, false);
+    forest.add(7, 8, 9, 10);
+    assert_eq!(&*forest.index_sizes(), &[3, 3]);
+    assert_eq!(forest.index_sizes().len() * 4, forest.built_index_orders().len());
+}
+
+#[wasm_bindgen_test]
+fn quads_since_a_watermark() {
+    let mut forest = ForestOfIdentifierQuads::new_from_identifier_list(&[1, 2, 3, 10]);
+    assert_eq!(forest.current_watermark(), 0);
+    assert!(forest.quads_since(0).is_empty());
+
+    forest.enable_insertion_sequence();
+    let watermark = forest.current_watermark();
+    assert_eq!(watermark, 1);
+
+    forest.add(4, 5, 6, 10);
+    forest.add(1, 2, 3, 10);
+    forest.add(7, 8, 9, 10);
+    assert_eq!(&*forest.quads_since(watermark), &[4, 5, 6, 10, 7, 8, 9, 10]);
+
+    forest.remove(4, 5, 6, 10);
+    assert_eq!(&*forest.quads_since(watermark), &[7, 8, 9, 10]);
+    assert_eq!(forest.current_watermark(), 3);
+    assert_eq!(&*forest.quads_since(0), &[1, 2, 3, 10, 7, 8, 9, 10]);
+}
+
+#[wasm_bindgen_test]
+fn retain_all_is_an_in_place_insersect() {
+    let identifier_list = [1, 2, 3, 10, 4, 5, 6, 10, 7, 8, 9, 20];
+    let mut forest = ForestOfIdentifierQuads::new_from_identifier_list(&identifier_list);
+    forest.ensure_has_index_for(true, false, false, false);
+    let other = ForestOfIdentifierQuads::new_from_identifier_list(&[4, 5, 6, 10, 7, 8, 9, 20, 1, 2, 3, 20]);
+
+    let expected = forest.insersect(&other);
+    forest.retain_all(&other);
+
+    assert!(forest.has_same_elements(&expected));
+    assert!(forest.check_consistency());
+    assert!(forest.get_all(Some(1), None, None, None).is_empty());
+}
+
+#[wasm_bindgen_test]
+fn top_degree_nodes_are_the_hubs() {
+    // 1 -> 2, 1 -> 3, 1 -> 4, 2 -> 3, 4 -> 3 in different graphs
+    let forest = ForestOfIdentifierQuads::new_from_identifier_list(&[
+        1, 100, 2, 0, 1, 100, 3, 0, 1, 101, 4, 10, 2, 100, 3, 0, 4, 100, 3, 10
+    ]);
+
+    assert_eq!(&*forest.top_degree_nodes(1, 0).unwrap(), &[1, 3]);
+    assert_eq!(&*forest.top_degree_nodes(2, 1).unwrap(), &[3, 3, 2, 1]);
+    assert_eq!(forest.top_degree_nodes(10, 0).unwrap().len(), 6);
+    assert!(forest.top_degree_nodes(1, 2).is_err());
+}
+
+#[wasm_bindgen_test]
+fn top_degree_nodes_do_not_depend_on_the_scan_order() {
+    let identifier_list = [
+        1, 100, 2, 0, 1, 100, 3, 0, 1, 101, 4, 10, 2, 100, 3, 0, 4, 100, 3, 10, 5, 100, 4, 0
+    ];
+    let mut forest = ForestOfIdentifierQuads::new_from_identifier_list(&identifier_list);
+
+    let out_degrees = forest.top_degree_nodes(3, 0).unwrap();
+    let in_degrees = forest.top_degree_nodes(3, 1).unwrap();
+    assert_eq!(&*out_degrees, &[1, 3, 2, 1, 4, 1]);
+    assert_eq!(&*in_degrees, &[3, 3, 4, 2, 2, 1]);
+
+    // The subjects are grouped by the frozen list and the objects by the base tree
+    forest.freeze();
+    assert_eq!(forest.top_degree_nodes(3, 0).unwrap(), out_degrees);
+    assert_eq!(forest.top_degree_nodes(3, 1).unwrap(), in_degrees);
+}
+
+#[wasm_bindgen_test]
+fn predicate_adjacency_in_csr_format() {
+    // With predicate 100: 5 -> 7, 5 -> 6, 2 -> 6 (twice), 9 -> 2
+    let forest = ForestOfIdentifierQuads::new_from_identifier_list(&[
+        5, 100, 7, 0, 5, 100, 6, 0, 2, 100, 6, 0, 2, 100, 6, 10, 9, 100, 2, 10, 5, 101, 9, 0
+    ]);
+
+    let csr = forest.predicate_adjacency_csr(100, None);
+    assert_eq!(&*csr.subjects(), &[2, 5, 9]);
+    assert_eq!(&*csr.row_offsets(), &[0, 1, 3, 4]);
+    assert_eq!(&*csr.targets(), &[6, 6, 7, 2]);
+
+    let csr = forest.predicate_adjacency_csr(100, Some(10));
+    assert_eq!(&*csr.subjects(), &[2, 9]);
+    assert_eq!(&*csr.row_offsets(), &[0, 1, 2]);
+    assert_eq!(&*csr.targets(), &[6, 2]);
+
+    let csr = forest.predicate_adjacency_csr(102, None);
+    assert!(csr.subjects().is_empty());
+    assert_eq!(&*csr.row_offsets(), &[0]);
+}
+
+#[wasm_bindgen_test]
+fn quads_with_the_sentinel_are_rejected() {
+    let mut forest = ForestOfIdentifierQuads::new_from_identifier_list(&[1, 2, 99, 10]);
+    forest.set_strict_sentinel(Some(99));
+
+    forest.add(99, 2, 3, 10);
+    forest.insert_from_identifier_list(&[4, 5, 6, 10, 7, 8, 9, 99]);
+    assert_eq!(forest.extend_from_slice(&[4, 99, 6, 10, 1, 2, 3, 10]), 1);
+
+    assert_eq!(forest.number_of_rejected_quads(), 3);
+    assert_eq!(sorted_quads(&forest.get_all(None, None, None, None)), vec![[1, 2, 3, 10], [1, 2, 99, 10], [4, 5, 6, 10]]);
+
+    forest.set_strict_sentinel(None);
+    forest.add(99, 2, 3, 10);
+    assert!(forest.has(99, 2, 3, 10));
+}
+
+#[wasm_bindgen_test]
+fn disjoint_datasets_have_no_common_quad() {
+    let forest = ForestOfIdentifierQuads::new_from_identifier_list(&[1, 2, 3, 10, 4, 5, 6, 10]);
+    let overlapping = ForestOfIdentifierQuads::new_from_identifier_list(&[4, 5, 6, 10, 7, 8, 9, 10]);
+    let disjoint = ForestOfIdentifierQuads::new_from_identifier_list(&[1, 2, 3, 20, 7, 8, 9, 10]);
+
+    assert!(!forest.is_disjoint_from(&overlapping));
+    assert!(forest.is_disjoint_from(&disjoint));
+    assert!(forest.is_disjoint_from(&ForestOfIdentifierQuads::new()));
+}
+
+#[wasm_bindgen_test]
+fn for_each_matching_streams_the_quads() {
+    let forest = ForestOfIdentifierQuads::new_from_identifier_list(&[1, 2, 3, 10, 1, 5, 6, 10, 7, 8, 9, 10]);
+
+    let visited = js_sys::Array::new();
+    let callback = js_sys::Function::new_with_args("quad", "this.push(Array.from(quad));");
+    let callback = callback.bind(&visited);
+    forest.for_each_matching(Some(1), None, None, None, &callback).unwrap();
+
+    let visited = visited.iter()
+        .flat_map(|quad| js_sys::Uint32Array::new(&quad).to_vec())
+        .collect::<Vec<u32>>();
+    assert_eq!(sorted_quads(&visited), vec![[1, 2, 3, 10], [1, 5, 6, 10]]);
+
+    let calls = js_sys::Array::new();
+    let throwing_callback = js_sys::Function::new_with_args("quad", "this.push(quad); throw new Error('stop');");
+    let throwing_callback = throwing_callback.bind(&calls);
+    assert!(forest.for_each_matching(None, None, None, None, &throwing_callback).is_err());
+    assert_eq!(calls.length(), 1);
+}
+
+#[wasm_bindgen_test]
+fn count_by_predicate_and_graph_sums_to_the_size() {
+    let forest = ForestOfIdentifierQuads::new_from_identifier_list(&[
+        1, 2, 3, 10, 4, 2, 6, 10, 1, 5, 3, 10, 1, 2, 3, 20, 4, 5, 6, 0
+    ]);
+
+    let counts = forest.count_by_predicate_and_graph();
+    assert_eq!(&*counts, &[0, 5, 1, 10, 2, 2, 10, 5, 1, 20, 2, 1]);
+    assert_eq!(counts.chunks(3).map(|record| record[2] as usize).sum::<usize>(), forest.size());
+}
+
+#[wasm_bindgen_test]
+fn contains_sorted_slice_is_contains_slice() {
+    let mut forest = ForestOfIdentifierQuads::new_from_identifier_list(&[1, 2, 3, 10, 4, 5, 6, 10, 7, 8, 9, 20, 1, 8, 6, 10]);
+
+    let all_quads = forest.get_all_in_base_order();
+    assert!(forest.contains_sorted_slice(&all_quads));
+    assert!(forest.contains_sorted_slice(&[]));
+
+    // Sorted in OGPS order, with a duplicated quad
+    assert!(forest.contains_sorted_slice(&[1, 2, 3, 10, 1, 2, 3, 10, 1, 8, 6, 10, 7, 8, 9, 20]));
+    assert!(!forest.contains_sorted_slice(&[1, 2, 3, 10, 2, 8, 6, 10, 7, 8, 9, 20]));
+    assert!(!forest.contains_sorted_slice(&[7, 8, 9, 20, 7, 8, 9, 30]));
+
+    forest.freeze();
+    assert!(forest.contains_sorted_slice(&all_quads));
+}
+
+#[wasm_bindgen_test]
+fn diff_by_graph_lists_the_changes_of_every_graph() {
+    let forest = ForestOfIdentifierQuads::new_from_identifier_list(&[1, 2, 3, 10, 4, 5, 6, 10, 7, 8, 9, 20]);
+    let other = ForestOfIdentifierQuads::new_from_identifier_list(&[1, 2, 3, 10, 4, 5, 7, 10, 7, 8, 9, 20, 1, 1, 1, 30]);
+
+    let diff = forest.diff_by_graph(&other).unwrap();
+    assert_eq!(diff.length(), 3);
+
+    let field = |i: u32, name: &str| js_sys::Reflect::get(&diff.get(i), &JsValue::from_str(name)).unwrap();
+    let quads = |i: u32, name: &str| js_sys::Uint32Array::new(&field(i, name)).to_vec();
+
+    assert_eq!(field(0, "graph").as_f64(), Some(10.0));
+    assert_eq!(quads(0, "added"), vec![4, 5, 7, 10]);
+    assert_eq!(quads(0, "removed"), vec![4, 5, 6, 10]);
+
+    assert_eq!(field(1, "graph").as_f64(), Some(20.0));
+    assert!(quads(1, "added").is_empty());
+    assert!(quads(1, "removed").is_empty());
+
+    assert_eq!(field(2, "graph").as_f64(), Some(30.0));
+    assert_eq!(quads(2, "added"), vec![1, 1, 1, 30]);
+    assert!(quads(2, "removed").is_empty());
+}
+
+#[wasm_bindgen_test]
+fn replace_graph_only_changes_the_graph() {
+    let mut forest = ForestOfIdentifierQuads::new_from_identifier_list(&[1, 2, 3, 10, 4, 5, 6, 10, 1, 2, 3, 20]);
+
+    forest.replace_graph(10, &[4, 5, 6, 7, 8, 9]);
+    assert_eq!(sorted_quads(&forest.get_all(None, None, None, None)),
+        vec![[1, 2, 3, 20], [4, 5, 6, 10], [7, 8, 9, 10]]);
+
+    forest.replace_graph(20, &[]);
+    assert!(!forest.has_graph(20));
+    assert_eq!(forest.size(), 2);
+}
+
+#[wasm_bindgen_test]
+fn distinct_subject_predicate_pairs() {
+    let forest = ForestOfIdentifierQuads::new_from_identifier_list(&[
+        1, 2, 3, 10, 1, 2, 4, 10, 1, 2, 3, 20, 1, 5, 3, 10, 4, 2, 6, 10
+    ]);
+
+    assert_eq!(&*forest.distinct_sp_pairs(), &[1, 2, 1, 5, 4, 2]);
+}
+
+#[wasm_bindgen_test]
+fn get_all_with_count_returns_a_page() {
+    let forest = ForestOfIdentifierQuads::new_from_identifier_list(&[
+        1, 2, 3, 10, 1, 5, 6, 10, 1, 8, 9, 10, 4, 5, 6, 10
+    ]);
+
+    let matching_quads = forest.get_all(Some(1), None, None, None);
+    let page = forest.get_all_with_count(Some(1), None, None, None, 1, 1);
+    assert_eq!(page.total(), 3);
+    assert_eq!(&*page.quads(), &matching_quads[4..8]);
+
+    let page = forest.get_all_with_count(Some(1), None, None, None, 5, 10);
+    assert_eq!(page.total(), 3);
+    assert!(page.quads().is_empty());
+
+    let all_quads = forest.get_all(None, None, None, None);
+    let page = forest.get_all_with_count(None, None, None, None, 2, 10);
+    assert_eq!(page.total(), 4);
+    assert_eq!(&*page.quads(), &all_quads[8..16]);
+}
+
+#[wasm_bindgen_test]
+fn locked_indexes_are_not_built_by_queries() {
+    let mut forest = ForestOfIdentifierQuads::new_from_identifier_list(&[1, 2, 3, 10, 4, 5, 6, 10]);
+    forest.lock_indexes(true);
+
+    assert_eq!(sorted_quads(&forest.get_all(Some(1), None, None, None)), vec![[1, 2, 3, 10]]);
+    assert_eq!(forest.match_count(None, Some(5), None, None), 1);
+    assert_eq!(forest.get_number_of_living_trees(), 1);
+
+    forest.lock_indexes(false);
+    forest.get_all(Some(1), None, None, None);
+    assert_eq!(forest.get_number_of_living_trees(), 2);
+}
+
+#[wasm_bindgen_test]
+fn has_each_returns_one_byte_per_quad() {
+    let forest = ForestOfIdentifierQuads::new_from_identifier_list(&[1, 2, 3, 10, 4, 5, 6, 10]);
+
+    assert_eq!(&*forest.has_each(&[4, 5, 6, 10, 7, 8, 9, 10, 1, 2, 3, 10]), &[1, 0, 1]);
+    assert!(forest.has_each(&[]).is_empty());
+}
+
+#[wasm_bindgen_test]
+fn shortest_path_follows_the_predicate() {
+    // 1 -> 2 -> 3 -> 4, 1 -> 5 -> 4 and 3 -> 1 with predicate 100, 1 -> 4 with predicate 101
+    let forest = ForestOfIdentifierQuads::new_from_identifier_list(&[
+        1, 100, 2, 0, 2, 100, 3, 0, 3, 100, 4, 0, 1, 100, 5, 10, 5, 100, 4, 10, 3, 100, 1, 0, 1, 101, 4, 0
+    ]);
+
+    assert_eq!(forest.shortest_path(1, 4, 100, None, None).as_deref(), Some(&[1, 5, 4][..]));
+    assert_eq!(forest.shortest_path(1, 4, 100, Some(0), None).as_deref(), Some(&[1, 2, 3, 4][..]));
+    assert_eq!(forest.shortest_path(3, 5, 100, None, None).as_deref(), Some(&[3, 1, 5][..]));
+    assert_eq!(forest.shortest_path(1, 1, 100, None, None).as_deref(), Some(&[1][..]));
+    assert_eq!(forest.shortest_path(4, 1, 100, None, None), None);
+    assert_eq!(forest.shortest_path(1, 4, 100, Some(0), Some(2)), None);
+}
+
+#[wasm_bindgen_test]
+fn cross_graph_duplicates_are_counted() {
+    let forest = ForestOfIdentifierQuads::new_from_identifier_list(&[
+        1, 2, 3, 10, 1, 2, 3, 20, 1, 2, 3, 30, 4, 5, 6, 10, 7, 8, 9, 0, 7, 8, 9, 10
+    ]);
+
+    assert_eq!(&*forest.cross_graph_duplicates(), &[1, 2, 3, 3, 7, 8, 9, 2]);
+}
+
+#[wasm_bindgen_test]
+fn add_returning_size_returns_the_new_size() {
+    let mut forest = ForestOfIdentifierQuads::new();
+    assert_eq!(forest.add_returning_size(1, 2, 3, 0), 1);
+    assert_eq!(forest.add_returning_size(4, 5, 6, 0), 2);
+    assert_eq!(forest.add_returning_size(1, 2, 3, 0), 2);
+}
+
+#[wasm_bindgen_test]
+fn complement_within_a_tiny_universe() {
+    let forest = ForestOfIdentifierQuads::new_from_identifier_list(&[0, 0, 0, 0, 1, 0, 1, 0, 5, 5, 5, 5]);
+
+    let complement = forest.complement_within(1, 0, 1, 0).unwrap();
+    assert_eq!(sorted_quads(&complement.get_all(None, None, None, None)), vec![[0, 0, 1, 0], [1, 0, 0, 0]]);
+
+    let mut forest = forest;
+    forest.set_max_complement_universe(3);
+    assert!(forest.complement_within(1, 0, 1, 0).is_err());
+    assert!(forest.complement_within(0, 0, 2, 0).is_ok());
+    assert!(forest.complement_within(u32::MAX, u32::MAX, u32::MAX, u32::MAX).is_err());
+}
+
+#[wasm_bindgen_test]
+fn count_with_value_at_counts_one_position() {
+    let forest = ForestOfIdentifierQuads::new_from_identifier_list(&[1, 2, 3, 0, 1, 5, 1, 0, 4, 2, 1, 1]);
+
+    assert_eq!(forest.count_with_value_at(0, 1).unwrap(), 2);
+    assert_eq!(forest.count_with_value_at(1, 2).unwrap(), 2);
+    assert_eq!(forest.count_with_value_at(2, 1).unwrap(), 2);
+    assert_eq!(forest.count_with_value_at(3, 1).unwrap(), 1);
+    assert_eq!(forest.count_with_value_at(0, 9).unwrap(), 0);
+    assert!(forest.count_with_value_at(4, 1).is_err());
+}
+
+#[wasm_bindgen_test]
+fn retain_matching_any_keeps_the_union_of_the_patterns() {
+    let mut forest = ForestOfIdentifierQuads::new_from_identifier_list(&[
+        1, 2, 3, 0, 1, 5, 6, 0, 4, 2, 3, 10, 7, 8, 9, 10, 7, 2, 1, 20
+    ]);
+    forest.ensure_has_index_for(true, false, false, false);
+
+    forest.retain_matching_any(&[1, -1, -1, -1, -1, -1, -1, 20, 1, 2, -1, -1]).unwrap();
+
+    assert_eq!(
+        sorted_quads(&forest.get_all(None, None, None, None)),
+        vec![[1, 2, 3, 0], [1, 5, 6, 0], [7, 2, 1, 20]]
+    );
+    assert_eq!(forest.get_number_of_living_trees(), 1);
+
+    assert!(forest.retain_matching_any(&[1, -2, -1, -1]).is_err());
+    assert_eq!(forest.size(), 3);
+
+    forest.retain_matching_any(&[]).unwrap();
+    assert_eq!(forest.size(), 0);
+}
+
+#[wasm_bindgen_test]
+fn estimate_match_count_assumes_independent_terms() {
+    let mut forest = ForestOfIdentifierQuads::new();
+    assert_eq!(forest.estimate_match_count(Some(1), None, None, None), 0);
+
+    for s in 0..4 {
+        for o in 0..5 {
+            forest.add(s, 10 + o % 2, o, 0);
+        }
+    }
+
+    assert_eq!(forest.estimate_match_count(None, None, None, None), 20);
+    assert_eq!(forest.estimate_match_count(Some(1), None, None, None), 5);
+    assert_eq!(forest.estimate_match_count(Some(1), Some(10), None, None), 3);
+    assert_eq!(forest.estimate_match_count(Some(1), None, Some(2), Some(0)), 1);
+
+    forest.add(4, 10, 0, 0);
+    assert_eq!(forest.estimate_match_count(Some(4), None, None, None), 4);
+}
+
+#[wasm_bindgen_test]
+fn statistics_are_maintained() {
+    let mut forest = ForestOfIdentifierQuads::new_from_identifier_list(&[1, 2, 3, 0, 1, 5, 6, 0]);
+    assert_eq!(&*forest.distinct_term_counts(), &[1, 2, 2, 1]);
+
+    forest.enable_statistics();
+    assert_eq!(&*forest.distinct_term_counts(), &[1, 2, 2, 1]);
+
+    forest.add(4, 2, 3, 10);
+    assert_eq!(&*forest.distinct_term_counts(), &[2, 2, 2, 2]);
+
+    forest.remove(1, 2, 3, 0);
+    assert_eq!(&*forest.distinct_term_counts(), &[2, 2, 2, 2]);
+
+    forest.remove(4, 2, 3, 10);
+    assert_eq!(&*forest.distinct_term_counts(), &[1, 1, 1, 1]);
+    assert_eq!(forest.estimate_match_count(Some(1), None, None, None), 1);
+
+    forest.disable_statistics();
+    forest.add(7, 8, 9, 0);
+    assert_eq!(&*forest.distinct_term_counts(), &[2, 2, 2, 1]);
+}
+
+#[wasm_bindgen_test]
+fn grouped_aggregations_do_not_depend_on_the_scan_order() {
+    let identifier_list = [
+        1, 2, 3, 10, 1, 2, 3, 20, 1, 2, 4, 10, 1, 5, 3, 10, 6, 2, 3, 10, 6, 2, 3, 0
+    ];
+    let mut forest = ForestOfIdentifierQuads::new_from_identifier_list(&identifier_list);
+    forest.lock_indexes(true);
+
+    let distinct_triples = forest.distinct_triples();
+    let distinct_sp_pairs = forest.distinct_sp_pairs();
+    let cross_graph_duplicates = forest.cross_graph_duplicates();
+    let counts = forest.count_by_predicate_and_graph();
+    let groups = forest.group_by_subject();
+    let bytes = forest.to_bytes_sorted();
+    assert_eq!(forest.get_number_of_living_trees(), 1);
+
+    assert_eq!(&*distinct_sp_pairs, &[1, 2, 1, 5, 6, 2]);
+    assert_eq!(&*cross_graph_duplicates, &[1, 2, 3, 2, 6, 2, 3, 2]);
+    assert_eq!(&*counts, &[0, 2, 1, 10, 2, 3, 10, 5, 1, 20, 2, 1]);
+
+    // Lists the quads in the tree that starts with the subject
+    forest.lock_indexes(false);
+    assert_eq!(forest.group_by_subject(), groups);
+    assert_eq!(forest.get_number_of_living_trees(), 2);
+
+    // The frozen list is sorted in SPOG order
+    forest.freeze();
+    assert_eq!(forest.distinct_triples(), distinct_triples);
+    assert_eq!(forest.distinct_sp_pairs(), distinct_sp_pairs);
+    assert_eq!(forest.cross_graph_duplicates(), cross_graph_duplicates);
+    assert_eq!(forest.count_by_predicate_and_graph(), counts);
+    assert_eq!(forest.group_by_subject(), groups);
+    assert_eq!(forest.to_bytes_sorted(), bytes);
+}